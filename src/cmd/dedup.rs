@@ -0,0 +1,176 @@
+use std::collections::{HashMap, HashSet};
+use std::io;
+
+use csv;
+
+use CliResult;
+use config::{Config, Delimiter};
+use normalize::KeyNormalizer;
+use select::{SelectColumns, Selection};
+use util::{self, ByteString};
+
+static USAGE: &'static str = "
+Removes duplicate rows from CSV data.
+
+Two rows are duplicates when they have the same values in the columns given
+with --select (or in every column when --select isn't given). Unlike
+'xsv sort --unique', the data doesn't need to be sorted and the rows that are
+kept are written in the same order as they appear in the input.
+
+By default, the first row of each set of duplicates is kept, which only
+requires memory proportional to the number of distinct keys. With --keep-last
+or --drop-all, all of the CSV data must be read into memory before anything
+is written.
+
+Usage:
+    xsv dedup [options] [<input>]
+
+dedup options:
+    -s, --select <arg>     Select the columns that make up the key used to
+                           find duplicates.
+                           See 'xsv select --help' for the format details.
+    --keep-last            Keep the last row of each set of duplicates
+                           instead of the first.
+    --drop-all             Drop every row that has a duplicate, i.e., only
+                           keep rows whose key is unique.
+    --trim                 Ignore leading and trailing whitespace when
+                           comparing keys.
+    --no-case              Compare keys case insensitively.
+    --normalize <list>     A comma separated list of normalizers applied to
+                           keys before they are compared, e.g., 'nfc,lower'.
+                           See 'xsv join --help' for the list.
+    --key-regex <regex>    Only compare the part of each key field matched
+                           by <regex> (or by its first capture group).
+    -D, --dupes-output <file>  Write the rows that were removed to <file>.
+
+Common options:
+    -h, --help             Display this message
+    -o, --output <file>    Write output to <file> instead of stdout.
+    -n, --no-headers       When set, the first row will not be interpreted
+                           as headers. Namely, it will be deduplicated with
+                           the rest of the rows. Otherwise, the first row will
+                           always appear as the header row in the output.
+    -d, --delimiter <arg>  The field delimiter for reading CSV data.
+                           Must be a single character. (default: ,)
+";
+
+#[derive(Deserialize)]
+struct Args {
+    arg_input: Option<String>,
+    flag_select: SelectColumns,
+    flag_keep_last: bool,
+    flag_drop_all: bool,
+    flag_trim: bool,
+    flag_no_case: bool,
+    flag_normalize: Option<String>,
+    flag_key_regex: Option<String>,
+    flag_dupes_output: Option<String>,
+    flag_output: Option<String>,
+    flag_no_headers: bool,
+    flag_delimiter: Option<Delimiter>,
+}
+
+pub fn run(argv: &[&str]) -> CliResult<()> {
+    let args: Args = util::get_args(USAGE, argv)?;
+    if args.flag_keep_last && args.flag_drop_all {
+        return fail!("Please pick at most one of --keep-last and --drop-all.");
+    }
+    let rconfig = Config::new(&args.arg_input)
+        .delimiter(args.flag_delimiter)
+        .no_headers(args.flag_no_headers)
+        .select(args.flag_select.clone());
+
+    let norm = KeyNormalizer::new()
+        .trim(args.flag_trim)
+        .lowercase(args.flag_no_case)
+        .steps(&args.flag_normalize)?
+        .regex(&args.flag_key_regex)?;
+
+    let mut rdr = rconfig.reader()?;
+    let headers = rdr.byte_headers()?.clone();
+    let sel = rconfig.selection(&headers)?;
+
+    let mut wtr = Config::new(&args.flag_output).writer()?;
+    rconfig.write_headers(&mut rdr, &mut wtr)?;
+    let mut dupes_wtr = match args.flag_dupes_output {
+        None => None,
+        Some(ref path) => {
+            let mut dupes_wtr = Config::new(&Some(path.clone())).writer()?;
+            rconfig.write_headers(&mut rdr, &mut dupes_wtr)?;
+            Some(dupes_wtr)
+        }
+    };
+
+    if args.flag_keep_last || args.flag_drop_all {
+        args.dedup_buffered(&sel, &norm, &mut rdr, &mut wtr, &mut dupes_wtr)?;
+    } else {
+        args.dedup_first(&sel, &norm, &mut rdr, &mut wtr, &mut dupes_wtr)?;
+    }
+    if let Some(mut dupes_wtr) = dupes_wtr {
+        dupes_wtr.flush()?;
+    }
+    Ok(wtr.flush()?)
+}
+
+impl Args {
+    /// Keep the first row of each set of duplicates in a single pass.
+    fn dedup_first<R: io::Read, W: io::Write>(
+        &self,
+        sel: &Selection,
+        norm: &KeyNormalizer,
+        rdr: &mut csv::Reader<R>,
+        wtr: &mut csv::Writer<W>,
+        dupes_wtr: &mut Option<csv::Writer<W>>,
+    ) -> CliResult<()> {
+        let mut seen = HashSet::new();
+        for row in rdr.byte_records() {
+            let row = row?;
+            if seen.insert(key(sel, norm, &row)) {
+                wtr.write_byte_record(&row)?;
+            } else if let Some(ref mut dupes_wtr) = *dupes_wtr {
+                dupes_wtr.write_byte_record(&row)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Keep the last row of each set of duplicates, or drop all rows that
+    /// have duplicates. Both need to see every row before writing any.
+    fn dedup_buffered<R: io::Read, W: io::Write>(
+        &self,
+        sel: &Selection,
+        norm: &KeyNormalizer,
+        rdr: &mut csv::Reader<R>,
+        wtr: &mut csv::Writer<W>,
+        dupes_wtr: &mut Option<csv::Writer<W>>,
+    ) -> CliResult<()> {
+        let all = rdr.byte_records().collect::<Result<Vec<_>, _>>()?;
+        // Maps each key to the index of its last row and its number of rows.
+        let mut keys: HashMap<Vec<ByteString>, (usize, usize)> =
+            HashMap::new();
+        for (i, row) in all.iter().enumerate() {
+            let entry = keys.entry(key(sel, norm, row)).or_insert((i, 0));
+            entry.0 = i;
+            entry.1 += 1;
+        }
+        for (i, row) in all.iter().enumerate() {
+            let (last, count) = keys[&key(sel, norm, row)];
+            let keep =
+                if self.flag_drop_all { count == 1 } else { last == i };
+            if keep {
+                wtr.write_byte_record(row)?;
+            } else if let Some(ref mut dupes_wtr) = *dupes_wtr {
+                dupes_wtr.write_byte_record(row)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+fn key(
+    sel: &Selection,
+    norm: &KeyNormalizer,
+    row: &csv::ByteRecord,
+) -> Vec<ByteString> {
+    sel.select(row).map(|v| norm.normalize(v)).collect()
+}
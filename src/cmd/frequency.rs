@@ -1,15 +1,18 @@
 use std::fs;
+use std::hash::Hash;
 use std::io;
 
 use channel;
 use csv;
-use stats::{Frequencies, merge_all};
+use stats::{Commute, Frequencies, merge_all};
 use threadpool::ThreadPool;
 
 use CliResult;
+use cmd::stats::{FieldType, Formats, show_edge};
 use config::{Config, Delimiter};
 use index::Indexed;
 use select::{SelectColumns, Selection};
+use sketch::{HyperLogLog, SpaceSaving};
 use util;
 
 static USAGE: &'static str = "
@@ -26,6 +29,32 @@ respectively.
 Since this computes an exact frequency table, memory proportional to the
 cardinality of each column is required.
 
+A table that is truncated by --limit ends with an '(other)' row holding the
+count of the remaining values, so that the counts always add up to the total
+(unless --no-other is set). With --percent, each row also has the share of the
+count in percent, and the cumulative share of the rows up to and including it.
+
+With --bin-width or --bin-edges, the numeric values of each field (those that
+'xsv stats' infers as Integer or Float) are counted by bins, which are
+labeled with their ranges, e.g., '[10, 20)'. Other values are counted as is.
+
+When --joint is set, the combinations of values of the selected fields are
+counted instead, and the table has one column for each selected field:
+
+    <field1>,<field2>,...,count
+
+When --heavy-hitters is set, only the most frequent values of each field are
+found, in memory bounded by --capacity (with the Space-Saving algorithm). The
+counts may then be too high, by at most the added 'error' column:
+
+    field,value,count,error
+
+When --approx-cardinality is set, no frequency table is computed. Instead,
+the number of distinct values in each field is estimated in constant memory
+(with HyperLogLog) and formatted as:
+
+    field,cardinality
+
 Usage:
     xsv frequency [options] [<input>]
 
@@ -41,6 +70,40 @@ frequency options:
     -a, --asc              Sort the frequency tables in ascending order by
                            count. The default is descending order.
     --no-nulls             Don't include NULLs in the frequency table.
+    --percent              Add 'percent' and 'cumulative_percent' columns.
+    --percent-of <base>    What the percentages are relative to: 'total'
+                           for the count of all values, or 'non-null' for
+                           the count of values that aren't NULL. In the
+                           latter case, the NULL row has no percentages.
+                           [default: total]
+    --no-other             When a table is truncated by --limit, don't add
+                           an '(other)' row with the count of the remaining
+                           values.
+    --bin-width <w>        Count numeric values in bins of width <w>,
+                           starting at multiples of <w>.
+    --bin-edges <edges>    Count numeric values in the bins between the
+                           given comma separated edges, which must be
+                           increasing, plus one bin below the first edge
+                           and one above the last.
+    --joint                Count the combinations of values of the selected
+                           fields, instead of the values of each field.
+                           With --no-nulls, rows with a NULL in any of the
+                           selected fields are skipped.
+    --heavy-hitters        Only find the most frequent values of each
+                           field, using bounded memory. Their counts are
+                           upper bounds, and 'count - error' is a lower
+                           bound.
+    --capacity <n>         The number of values tracked per field with
+                           heavy hitters. A value that occurs in more than
+                           1/<n> of the rows is always found, and no error
+                           is larger than that. [default: 1000]
+    --approx-cardinality   Only report the approximate number of distinct
+                           values in each field.
+    --precision <p>        The precision used by --approx-cardinality,
+                           between 4 and 18. It uses 2^p bytes per field
+                           and has a standard error of about
+                           1.04 / sqrt(2^p), e.g., 0.8% for 14.
+                           [default: 14]
     -j, --jobs <arg>       The number of jobs to run in parallel.
                            This works better when the given CSV data has
                            an index already created. Note that a file handle
@@ -67,6 +130,16 @@ struct Args {
     flag_limit: usize,
     flag_asc: bool,
     flag_no_nulls: bool,
+    flag_percent: bool,
+    flag_percent_of: String,
+    flag_no_other: bool,
+    flag_bin_width: Option<f64>,
+    flag_bin_edges: Option<String>,
+    flag_joint: bool,
+    flag_heavy_hitters: bool,
+    flag_capacity: usize,
+    flag_approx_cardinality: bool,
+    flag_precision: usize,
     flag_jobs: usize,
     flag_output: Option<String>,
     flag_no_headers: bool,
@@ -75,6 +148,26 @@ struct Args {
 
 pub fn run(argv: &[&str]) -> CliResult<()> {
     let args: Args = util::get_args(USAGE, argv)?;
+    HyperLogLog::check_precision(args.flag_precision)?;
+    if args.flag_joint && args.flag_approx_cardinality {
+        return fail!("--joint cannot be used with --approx-cardinality.");
+    }
+    if args.flag_percent_of != "total" && args.flag_percent_of != "non-null" {
+        return fail!(format!(
+            "Unknown percentage base '{}'. Expected 'total' or 'non-null'.",
+            args.flag_percent_of));
+    }
+    if args.flag_heavy_hitters {
+        if args.flag_capacity == 0 {
+            return fail!("--capacity must be greater than 0.");
+        }
+        if args.flag_joint || args.flag_approx_cardinality || args.flag_asc
+                || args.flag_percent {
+            return fail!("--heavy-hitters cannot be used with --joint, \
+                          --approx-cardinality, --asc or --percent.");
+        }
+    }
+    args.bins()?;
     let rconfig = args.rconfig();
 
     let mut wtr = Config::new(&args.flag_output).writer()?;
@@ -83,17 +176,75 @@ pub fn run(argv: &[&str]) -> CliResult<()> {
         _ => args.sequential_ftables(),
     }?;
 
-    wtr.write_record(vec!["field", "value", "count"])?;
+    if args.flag_joint {
+        let mut names: csv::ByteRecord =
+            if rconfig.no_headers {
+                (1..headers.len() + 1).map(|i| i.to_string()).collect()
+            } else {
+                headers
+            };
+        let nvalues = names.len();
+        names.extend(args.count_headers());
+        wtr.write_record(&names)?;
+        if let Some(Table::Joint(ftab)) = tables.into_iter().next() {
+            let is_null = |vs: &Vec<ByteString>| {
+                vs.iter().any(|v| v.is_empty())
+            };
+            for (values, counts) in args.rows(&ftab, is_null) {
+                let mut row: Vec<ByteString> = match values {
+                    None => vec![b"(other)".to_vec(); nvalues],
+                    Some(vs) => vs.into_iter().map(show_null).collect(),
+                };
+                row.extend(counts.into_iter().map(String::into_bytes));
+                wtr.write_record(&row)?;
+            }
+        }
+        return Ok(());
+    }
+
+    if args.flag_approx_cardinality {
+        wtr.write_record(vec!["field", "cardinality"])?;
+    } else if args.flag_heavy_hitters {
+        wtr.write_record(vec!["field", "value", "count", "error"])?;
+    } else {
+        let mut names = vec!["field", "value"];
+        names.extend(args.count_headers());
+        wtr.write_record(names)?;
+    }
     let head_ftables = headers.into_iter().zip(tables.into_iter());
-    for (i, (header, ftab)) in head_ftables.enumerate() {
+    for (i, (header, table)) in head_ftables.enumerate() {
         let mut header = header.to_vec();
         if rconfig.no_headers {
             header = (i+1).to_string().into_bytes();
         }
-        for (value, count) in args.counts(&ftab).into_iter() {
-            let count = count.to_string();
-            let row = vec![&*header, &*value, count.as_bytes()];
-            wtr.write_record(row)?;
+        match table {
+            Table::Exact(ftab) => {
+                for (value, counts) in args.rows(&ftab, |v| v.is_empty()) {
+                    let value = value.map_or(b"(other)".to_vec(), show_null);
+                    let mut row = vec![header.clone(), value];
+                    row.extend(counts.into_iter().map(String::into_bytes));
+                    wtr.write_record(&row)?;
+                }
+            }
+            Table::Approx(hll) => {
+                let count = hll.count().to_string();
+                wtr.write_record(vec![&*header, count.as_bytes()])?;
+            }
+            Table::TopK(ss) => {
+                let mut top = ss.top();
+                if args.flag_limit > 0 {
+                    top.truncate(args.flag_limit);
+                }
+                for (value, count, error) in top {
+                    wtr.write_record(vec![
+                        header.clone(),
+                        show_null(value.clone()),
+                        count.to_string().into_bytes(),
+                        error.to_string().into_bytes(),
+                    ])?;
+                }
+            }
+            Table::Joint(_) => unreachable!(),
         }
     }
     Ok(())
@@ -102,7 +253,112 @@ pub fn run(argv: &[&str]) -> CliResult<()> {
 type ByteString = Vec<u8>;
 type Headers = csv::ByteRecord;
 type FTable = Frequencies<Vec<u8>>;
-type FTables = Vec<Frequencies<Vec<u8>>>;
+type FTables = Vec<Table>;
+
+/// Table is what is computed for each field: either an exact frequency
+/// table, a heavy hitters summary with --heavy-hitters or, with
+/// --approx-cardinality, a cardinality sketch. With --joint, there is a
+/// single table of the combinations of all fields.
+enum Table {
+    Exact(FTable),
+    TopK(SpaceSaving),
+    Approx(HyperLogLog),
+    Joint(Frequencies<Vec<ByteString>>),
+}
+
+impl Table {
+    fn add(&mut self, field: ByteString) {
+        match *self {
+            Table::Exact(ref mut ftab) => ftab.add(field),
+            Table::TopK(ref mut ss) => ss.add(field),
+            Table::Approx(ref mut hll) => hll.add(&field),
+            Table::Joint(_) => unreachable!(),
+        }
+    }
+}
+
+impl Commute for Table {
+    fn merge(&mut self, other: Table) {
+        match (self, other) {
+            (&mut Table::Exact(ref mut t1), Table::Exact(t2)) => t1.merge(t2),
+            (&mut Table::TopK(ref mut t1), Table::TopK(t2)) => t1.merge(t2),
+            (&mut Table::Approx(ref mut t1), Table::Approx(t2)) => {
+                t1.merge(t2)
+            }
+            (&mut Table::Joint(ref mut t1), Table::Joint(t2)) => t1.merge(t2),
+            _ => unreachable!(),
+        }
+    }
+}
+
+/// Bins groups the numeric values of a field into ranges.
+enum Bins {
+    Width(f64),
+    Edges(Vec<f64>),
+}
+
+impl Bins {
+    /// Replace a numeric value with the label of its bin.
+    fn bin(&self, field: ByteString, formats: &Formats) -> ByteString {
+        let n = match FieldType::from_sample(&field, formats) {
+            FieldType::TInteger | FieldType::TFloat => {
+                String::from_utf8_lossy(&field).parse::<f64>().ok()
+            }
+            _ => None,
+        };
+        let n = match n {
+            Some(n) if n.is_finite() => n,
+            _ => return field,
+        };
+        let (lo, hi) = match *self {
+            Bins::Width(w) => {
+                // Bin on a grid scaled by the power of ten that makes the
+                // width whole, since a width like 0.1 has no exact binary
+                // representation and 0.3 / 0.1 = 2.9999999999999996.
+                let scale = match decimal_scale(w) {
+                    scale if (n * scale).is_finite() => scale,
+                    _ => 1.0,
+                };
+                let (n, w) = (n * scale, w * scale);
+                let mut i = (n / w).floor();
+                // The quotient can still be off by one near an edge, so
+                // settle on the bin that actually contains n.
+                if (i + 1.0) * w <= n {
+                    i += 1.0;
+                } else if i * w > n {
+                    i -= 1.0;
+                }
+                (Some(i * w / scale), Some((i + 1.0) * w / scale))
+            }
+            Bins::Edges(ref edges) => {
+                let i = edges.iter().take_while(|&&e| e <= n).count();
+                let lo = if i == 0 { None } else { Some(edges[i - 1]) };
+                (lo, edges.get(i).cloned())
+            }
+        };
+        let lo = lo.map_or("(-inf".to_owned(), |e| {
+            format!("[{}", show_edge(e))
+        });
+        let hi = hi.map_or("inf)".to_owned(), |e| {
+            format!("{})", show_edge(e))
+        });
+        format!("{}, {}", lo, hi).into_bytes()
+    }
+}
+
+/// Returns the smallest power of ten that makes `w` a whole number, or 1 if
+/// there is none that is small enough.
+fn decimal_scale(w: f64) -> f64 {
+    let mut scale = 1.0;
+    for _ in 0..10 {
+        let scaled = w * scale;
+        if scaled == scaled.round() {
+            return scale;
+        }
+        scale *= 10.0;
+    }
+    1.0
+}
 
 impl Args {
     fn rconfig(&self) -> Config {
@@ -112,22 +368,102 @@ impl Args {
             .select(self.flag_select.clone())
     }
 
-    fn counts(&self, ftab: &FTable) -> Vec<(ByteString, u64)> {
+    fn bins(&self) -> CliResult<Option<Bins>> {
+        match (self.flag_bin_width, &self.flag_bin_edges) {
+            (Some(_), &Some(_)) => {
+                fail!("--bin-width cannot be used with --bin-edges.")
+            }
+            (Some(w), &None) => {
+                if !(w > 0.0 && w.is_finite()) {
+                    return fail!("--bin-width must be a positive number.");
+                }
+                Ok(Some(Bins::Width(w)))
+            }
+            (None, &Some(ref edges)) => {
+                let mut parsed = vec![];
+                for edge in edges.split(',') {
+                    match edge.trim().parse::<f64>() {
+                        Ok(e) if e.is_finite() => parsed.push(e),
+                        _ => return fail!(format!(
+                            "Invalid bin edge '{}'.", edge)),
+                    }
+                }
+                if parsed.windows(2).any(|w| w[0] >= w[1]) {
+                    return fail!("Bin edges must be increasing.");
+                }
+                Ok(Some(Bins::Edges(parsed)))
+            }
+            (None, &None) => Ok(None),
+        }
+    }
+
+    fn count_headers(&self) -> Vec<&'static str> {
+        if self.flag_percent {
+            vec!["count", "percent", "cumulative_percent"]
+        } else {
+            vec!["count"]
+        }
+    }
+
+    /// Returns the rows of a frequency table in order: the value, which is
+    /// `None` for the `(other)` row, and the count followed by the
+    /// percentages (if enabled).
+    fn rows<T, F>(
+        &self,
+        ftab: &Frequencies<T>,
+        is_null: F,
+    ) -> Vec<(Option<T>, Vec<String>)>
+            where T: Clone + Eq + Hash, F: Fn(&T) -> bool {
         let mut counts = if self.flag_asc {
             ftab.least_frequent()
         } else {
             ftab.most_frequent()
         };
-        if self.flag_limit > 0 {
-            counts = counts.into_iter().take(self.flag_limit).collect();
+        let sum = |counts: &[(&T, u64)], nulls: bool| -> u64 {
+            counts.iter()
+                  .filter(|&&(v, _)| !nulls || is_null(v))
+                  .map(|&(_, c)| c)
+                  .sum()
+        };
+        let (total, nulls) = (sum(&counts, false), sum(&counts, true));
+        let (mut other, mut other_nulls) = (0, 0);
+        if self.flag_limit > 0 && counts.len() > self.flag_limit {
+            let rest = counts.split_off(self.flag_limit);
+            other = sum(&rest, false);
+            other_nulls = sum(&rest, true);
         }
-        counts.into_iter().map(|(bs, c)| {
-            if b"" == &**bs {
-                (b"(NULL)"[..].to_vec(), c)
-            } else {
-                (bs.clone(), c)
+
+        // NULLs don't count towards the percentages of non-null values.
+        let non_null = self.flag_percent_of == "non-null";
+        let base = if non_null { total - nulls } else { total };
+        let mut cumulative = 0;
+        let mut fields = |count: u64, counted: Option<u64>| {
+            let mut fields = vec![count.to_string()];
+            if self.flag_percent {
+                match counted {
+                    None => {
+                        fields.push("".to_owned());
+                        fields.push("".to_owned());
+                    }
+                    Some(n) => {
+                        cumulative += n;
+                        fields.push(percent(n, base));
+                        fields.push(percent(cumulative, base));
+                    }
+                }
             }
-        }).collect()
+            fields
+        };
+        let mut rows = vec![];
+        for (v, c) in counts {
+            let counted = if non_null && is_null(v) { None } else { Some(c) };
+            rows.push((Some(v.clone()), fields(c, counted)));
+        }
+        if !self.flag_no_other && other > 0 {
+            let counted = if non_null { other - other_nulls } else { other };
+            rows.push((None, fields(other, Some(counted))));
+        }
+        rows
     }
 
     fn sequential_ftables(&self) -> CliResult<(Headers, FTables)> {
@@ -167,12 +503,39 @@ impl Args {
             where I: Iterator<Item=csv::Result<csv::ByteRecord>> {
         let null = &b""[..].to_vec();
         let nsel = sel.normal();
-        let mut tabs: Vec<_> =
-            (0..nsel.len()).map(|_| Frequencies::new()).collect();
+        let (bins, formats) = (self.bins()?, Formats::default());
+        let bin = |field: ByteString| match bins {
+            Some(ref bins) => bins.bin(field, &formats),
+            None => field,
+        };
+        if self.flag_joint {
+            let mut tab = Frequencies::new();
+            for row in it {
+                let row = row?;
+                // Keep the order of the selection, unlike `nsel`.
+                let fields: Vec<ByteString> = sel.select(&row)
+                    .map(|f| bin(trim(f.to_vec())))
+                    .collect();
+                if self.flag_no_nulls && fields.iter().any(|f| f.is_empty()) {
+                    continue;
+                }
+                tab.add(fields);
+            }
+            return Ok(vec![Table::Joint(tab)]);
+        }
+        let mut tabs: Vec<_> = (0..nsel.len()).map(|_| {
+            if self.flag_approx_cardinality {
+                Table::Approx(HyperLogLog::new(self.flag_precision as u8))
+            } else if self.flag_heavy_hitters {
+                Table::TopK(SpaceSaving::new(self.flag_capacity))
+            } else {
+                Table::Exact(Frequencies::new())
+            }
+        }).collect();
         for row in it {
             let row = row?;
             for (i, field) in nsel.select(row.into_iter()).enumerate() {
-                let field = trim(field.to_vec());
+                let field = bin(trim(field.to_vec()));
                 if !field.is_empty() {
                     tabs[i].add(field);
                 } else {
@@ -197,6 +560,18 @@ impl Args {
     }
 }
 
+/// Show `n` as a percentage of `base`, rounded to two decimals.
+fn percent(n: u64, base: u64) -> String {
+    if base == 0 {
+        return "".to_owned();
+    }
+    format!("{:.2}", 100.0 * n as f64 / base as f64)
+}
+
+fn show_null(bs: ByteString) -> ByteString {
+    if bs.is_empty() { b"(NULL)"[..].to_vec() } else { bs }
+}
+
 fn trim(bs: ByteString) -> ByteString {
     match String::from_utf8(bs) {
         Ok(s) => s.trim().as_bytes().to_vec(),
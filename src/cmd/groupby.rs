@@ -0,0 +1,528 @@
+use std::collections::{HashMap, HashSet};
+use std::fs;
+
+use channel;
+use csv;
+use stats::{Commute, OnlineStats};
+use threadpool::ThreadPool;
+
+use CliResult;
+use config::{Config, Delimiter};
+use cmd::stats::{FieldType, Formats, TypedMinMax, TypedSum};
+use index::Indexed;
+use select::{SelectColumns, Selection};
+use util::{self, ByteString};
+
+static USAGE: &'static str = "
+Groups rows of CSV data by the values in some columns and computes aggregates
+of other columns for each group.
+
+The output has one row for each distinct value of the <group> columns, in the
+order in which they first appear. It contains the group columns followed by
+one column for each aggregation.
+
+<aggregations> is a comma separated list of aggregations, each of which is a
+function applied to a column, e.g., 'sum(revenue),count_distinct(user)'.
+Columns are referenced by name or by index (starting at 1), like with
+'xsv select'. The available functions are:
+
+    count()            The number of rows in the group.
+    count(col)         The number of non-empty values of col.
+    sum(col)           The sum of the values of col.
+    min(col)           The smallest value of col.
+    max(col)           The largest value of col.
+    mean(col)          The mean of the numeric values of col.
+    stddev(col)        The standard deviation of the numeric values of col.
+    first(col)         The first non-empty value of col.
+    last(col)          The last non-empty value of col.
+    count_distinct(col)  The number of distinct non-empty values of col.
+    concat(col)        The non-empty values of col, joined by --separator.
+
+Like 'xsv stats', sum, min and max compare numbers when all values of a column
+in a group are numbers and strings otherwise. (A sum of strings is empty.)
+
+Grouping a large file can be made much faster if you create an index for it
+first with 'xsv index'. All groups are kept in memory.
+
+Usage:
+    xsv groupby [options] <group> <aggregations> [<input>]
+    xsv groupby --help
+
+groupby options:
+    --separator <arg>      The separator used to join the values of concat.
+                           [default: ;]
+    -j, --jobs <arg>       The number of jobs to run in parallel.
+                           This works only when the given CSV data has
+                           an index already created. Note that a file handle
+                           is opened for each job.
+                           When set to '0', the number of jobs is set to the
+                           number of CPUs detected.
+                           [default: 0]
+
+Common options:
+    -h, --help             Display this message
+    -o, --output <file>    Write output to <file> instead of stdout.
+    -n, --no-headers       When set, the first row will NOT be interpreted
+                           as column names, so columns must be referenced by
+                           index. No header row is written either.
+    -d, --delimiter <arg>  The field delimiter for reading CSV data.
+                           Must be a single character. (default: ,)
+";
+
+#[derive(Clone, Deserialize)]
+struct Args {
+    arg_group: SelectColumns,
+    arg_aggregations: String,
+    arg_input: Option<String>,
+    flag_separator: String,
+    flag_jobs: usize,
+    flag_output: Option<String>,
+    flag_no_headers: bool,
+    flag_delimiter: Option<Delimiter>,
+}
+
+pub fn run(argv: &[&str]) -> CliResult<()> {
+    let args: Args = util::get_args(USAGE, argv)?;
+    let specs = parse_aggregations(&args.arg_aggregations)?;
+
+    let mut rdr = args.rconfig().reader()?;
+    let headers = rdr.byte_headers()?.clone();
+    let sel = args.rconfig().selection(&headers)?;
+    let mut aggs = vec![];
+    for spec in &specs {
+        aggs.push(spec.resolve(&headers, !args.flag_no_headers)?);
+    }
+
+    let groups = match args.rconfig().indexed()? {
+        Some(ref idx) if args.njobs() > 1 && idx.count() > 0 => {
+            args.parallel_groups(idx, &sel, &aggs)?
+        }
+        _ => args.compute(&sel, &aggs, rdr.byte_records())?,
+    };
+
+    let mut wtr = Config::new(&args.flag_output).writer()?;
+    if !args.flag_no_headers {
+        let mut record: csv::ByteRecord = sel.select(&headers).collect();
+        for spec in &specs {
+            record.push_field(spec.name.as_bytes());
+        }
+        wtr.write_byte_record(&record)?;
+    }
+    for (key, aggregators) in groups.groups {
+        let mut record: csv::ByteRecord = key.iter().collect();
+        for agg in &aggregators {
+            record.push_field(agg.show(&args.flag_separator).as_bytes());
+        }
+        wtr.write_byte_record(&record)?;
+    }
+    Ok(wtr.flush()?)
+}
+
+impl Args {
+    fn parallel_groups(
+        &self,
+        idx: &Indexed<fs::File, fs::File>,
+        sel: &Selection,
+        aggs: &[Aggregation],
+    ) -> CliResult<Groups> {
+        let chunk_size = util::chunk_size(idx.count() as usize, self.njobs());
+        let nchunks = util::num_of_chunks(idx.count() as usize, chunk_size);
+
+        let pool = ThreadPool::new(self.njobs());
+        let (send, recv) = channel::bounded(0);
+        for i in 0..nchunks {
+            let (send, args) = (send.clone(), self.clone());
+            let (sel, aggs) = (sel.clone(), aggs.to_vec());
+            pool.execute(move || {
+                let mut idx = args.rconfig().indexed().unwrap().unwrap();
+                idx.seek((i * chunk_size) as u64).unwrap();
+                let it = idx.byte_records().take(chunk_size);
+                send.send((i, args.compute(&sel, &aggs, it)));
+            });
+        }
+        drop(send);
+
+        // Groups are written in order of first appearance, so the chunks
+        // must be merged in the order they appear in the input.
+        let mut chunks: Vec<Option<Groups>> = vec![None; nchunks];
+        for (i, groups) in recv {
+            chunks[i] = Some(groups?);
+        }
+        let mut all = Groups::default();
+        for groups in chunks.into_iter() {
+            all.merge(groups.unwrap());
+        }
+        Ok(all)
+    }
+
+    fn compute<I>(
+        &self,
+        sel: &Selection,
+        aggs: &[Aggregation],
+        it: I,
+    ) -> CliResult<Groups>
+            where I: Iterator<Item=csv::Result<csv::ByteRecord>> {
+        let formats = Formats::default();
+        let mut groups = Groups::default();
+        for row in it {
+            let row = row?;
+            groups.add(sel, aggs, &row, &formats);
+        }
+        Ok(groups)
+    }
+
+    fn rconfig(&self) -> Config {
+        Config::new(&self.arg_input)
+            .delimiter(self.flag_delimiter)
+            .no_headers(self.flag_no_headers)
+            .select(self.arg_group.clone())
+    }
+
+    fn njobs(&self) -> usize {
+        if self.flag_jobs == 0 { util::num_cpus() } else { self.flag_jobs }
+    }
+}
+
+/// Groups holds the aggregators of every group, in order of first
+/// appearance.
+#[derive(Clone, Default)]
+struct Groups {
+    index: HashMap<Vec<ByteString>, usize>,
+    groups: Vec<(Vec<ByteString>, Vec<Aggregator>)>,
+}
+
+impl Groups {
+    fn add(
+        &mut self,
+        sel: &Selection,
+        aggs: &[Aggregation],
+        row: &csv::ByteRecord,
+        formats: &Formats,
+    ) {
+        let key: Vec<ByteString> =
+            sel.select(row).map(|v| v.to_vec()).collect();
+        let i = match self.index.get(&key) {
+            Some(&i) => i,
+            None => {
+                let i = self.groups.len();
+                let aggregators =
+                    aggs.iter().map(|a| a.aggregator()).collect();
+                self.index.insert(key.clone(), i);
+                self.groups.push((key, aggregators));
+                i
+            }
+        };
+        for (agg, aggregator) in aggs.iter().zip(&mut self.groups[i].1) {
+            aggregator.add(agg.column.map(|c| &row[c]), formats);
+        }
+    }
+
+    /// Merge the groups computed from rows that come after the rows of
+    /// these groups.
+    fn merge(&mut self, other: Groups) {
+        for (key, aggregators) in other.groups {
+            match self.index.get(&key) {
+                Some(&i) => {
+                    let ours = &mut self.groups[i].1;
+                    for (agg, theirs) in ours.iter_mut().zip(aggregators) {
+                        agg.merge(theirs);
+                    }
+                }
+                None => {
+                    self.index.insert(key.clone(), self.groups.len());
+                    self.groups.push((key, aggregators));
+                }
+            }
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Func {
+    Count,
+    Sum,
+    Min,
+    Max,
+    Mean,
+    Stddev,
+    First,
+    Last,
+    CountDistinct,
+    Concat,
+}
+
+impl Func {
+    fn from_name(name: &str) -> Option<Func> {
+        Some(match name {
+            "count" => Func::Count,
+            "sum" => Func::Sum,
+            "min" => Func::Min,
+            "max" => Func::Max,
+            "mean" => Func::Mean,
+            "stddev" => Func::Stddev,
+            "first" => Func::First,
+            "last" => Func::Last,
+            "count_distinct" => Func::CountDistinct,
+            "concat" => Func::Concat,
+            _ => return None,
+        })
+    }
+}
+
+/// An aggregation as given on the command line.
+struct AggSpec {
+    /// The aggregation as written, which names its column in the output.
+    name: String,
+    func: Func,
+    /// The column to aggregate, which is only missing for `count()`.
+    column: Option<SelectColumns>,
+}
+
+impl AggSpec {
+    /// Find the column of this aggregation in the headers.
+    fn resolve(
+        &self,
+        headers: &csv::ByteRecord,
+        use_names: bool,
+    ) -> CliResult<Aggregation> {
+        let column = match self.column {
+            None => None,
+            Some(ref column) => {
+                let sel = column.selection(headers, use_names)?;
+                if sel.len() != 1 {
+                    return fail!(format!(
+                        "Aggregation '{}' must refer to exactly one column, \
+                         but it refers to {}.", self.name, sel.len()));
+                }
+                Some(sel[0])
+            }
+        };
+        Ok(Aggregation { func: self.func, column: column })
+    }
+}
+
+/// Split a list of aggregations like `sum(a),count()` on the commas that
+/// aren't inside parentheses.
+fn parse_aggregations(s: &str) -> CliResult<Vec<AggSpec>> {
+    let (mut specs, mut depth, mut start) = (vec![], 0, 0);
+    for (i, c) in s.char_indices() {
+        match c {
+            '(' => depth += 1,
+            ')' => depth -= 1,
+            ',' if depth == 0 => {
+                specs.push(parse_aggregation(&s[start..i])?);
+                start = i + 1;
+            }
+            _ => {}
+        }
+    }
+    specs.push(parse_aggregation(&s[start..])?);
+    Ok(specs)
+}
+
+fn parse_aggregation(s: &str) -> CliResult<AggSpec> {
+    let s = s.trim();
+    let (name, arg) = match (s.find('('), s.ends_with(')')) {
+        (Some(open), true) => (&s[..open], s[open + 1..s.len() - 1].trim()),
+        _ => return fail!(format!(
+            "Invalid aggregation '{}'. Aggregations look like 'sum(column)'.",
+            s)),
+    };
+    let func = match Func::from_name(name.trim()) {
+        Some(func) => func,
+        None => return fail!(format!(
+            "Unknown aggregation function '{}'. Expected one of: count, sum, \
+             min, max, mean, stddev, first, last, count_distinct, concat.",
+            name)),
+    };
+    let column = if arg.is_empty() {
+        if func != Func::Count {
+            return fail!(format!(
+                "Aggregation '{}' needs a column.", s));
+        }
+        None
+    } else {
+        Some(SelectColumns::parse(arg)?)
+    };
+    Ok(AggSpec { name: s.to_owned(), func: func, column: column })
+}
+
+/// An aggregation with its column resolved to an index.
+#[derive(Clone, Copy, Debug)]
+struct Aggregation {
+    func: Func,
+    column: Option<usize>,
+}
+
+impl Aggregation {
+    fn aggregator(&self) -> Aggregator {
+        match self.func {
+            Func::Count => Aggregator::Count(0),
+            Func::Sum => {
+                Aggregator::Sum(FieldType::default(), TypedSum::default())
+            }
+            Func::Min => {
+                Aggregator::Min(FieldType::default(), TypedMinMax::default())
+            }
+            Func::Max => {
+                Aggregator::Max(FieldType::default(), TypedMinMax::default())
+            }
+            Func::Mean => Aggregator::Mean(OnlineStats::new()),
+            Func::Stddev => Aggregator::Stddev(OnlineStats::new()),
+            Func::First => Aggregator::First(None),
+            Func::Last => Aggregator::Last(None),
+            Func::CountDistinct => Aggregator::CountDistinct(HashSet::new()),
+            Func::Concat => Aggregator::Concat(vec![]),
+        }
+    }
+}
+
+/// Aggregator computes one aggregation for one group.
+#[derive(Clone)]
+enum Aggregator {
+    Count(u64),
+    Sum(FieldType, TypedSum),
+    Min(FieldType, TypedMinMax),
+    Max(FieldType, TypedMinMax),
+    Mean(OnlineStats),
+    Stddev(OnlineStats),
+    First(Option<ByteString>),
+    Last(Option<ByteString>),
+    CountDistinct(HashSet<ByteString>),
+    Concat(Vec<ByteString>),
+}
+
+impl Aggregator {
+    /// Add the value of the aggregated column of a row, which is `None`
+    /// for `count()`.
+    fn add(&mut self, sample: Option<&[u8]>, formats: &Formats) {
+        let sample = match sample {
+            None => {
+                if let Aggregator::Count(ref mut n) = *self {
+                    *n += 1;
+                }
+                return;
+            }
+            Some(sample) => sample,
+        };
+        if sample.is_empty() {
+            return;
+        }
+        match *self {
+            Aggregator::Count(ref mut n) => *n += 1,
+            Aggregator::Sum(ref mut typ, ref mut sum) => {
+                typ.merge(FieldType::from_sample(sample, formats));
+                sum.add(*typ, sample);
+            }
+            Aggregator::Min(ref mut typ, ref mut minmax)
+            | Aggregator::Max(ref mut typ, ref mut minmax) => {
+                typ.merge(FieldType::from_sample(sample, formats));
+                minmax.add(*typ, sample, formats);
+            }
+            Aggregator::Mean(ref mut online)
+            | Aggregator::Stddev(ref mut online) => {
+                if let Some(n) = from_bytes(sample) {
+                    online.add(n);
+                }
+            }
+            Aggregator::First(ref mut first) => {
+                if first.is_none() {
+                    *first = Some(sample.to_vec());
+                }
+            }
+            Aggregator::Last(ref mut last) => *last = Some(sample.to_vec()),
+            Aggregator::CountDistinct(ref mut set) => {
+                set.insert(sample.to_vec());
+            }
+            Aggregator::Concat(ref mut values) => values.push(sample.to_vec()),
+        }
+    }
+
+    fn show(&self, separator: &str) -> String {
+        let lossy = |s: &[u8]| String::from_utf8_lossy(s).into_owned();
+        match *self {
+            Aggregator::Count(n) => n.to_string(),
+            Aggregator::Sum(typ, ref sum) => {
+                sum.show(typ).unwrap_or_else(String::new)
+            }
+            Aggregator::Min(typ, ref minmax) => {
+                minmax.show(typ).map_or_else(String::new, |mm| mm.0)
+            }
+            Aggregator::Max(typ, ref minmax) => {
+                minmax.show(typ).map_or_else(String::new, |mm| mm.1)
+            }
+            Aggregator::Mean(ref online) => {
+                if online.len() == 0 {
+                    String::new()
+                } else {
+                    online.mean().to_string()
+                }
+            }
+            Aggregator::Stddev(ref online) => {
+                if online.len() == 0 {
+                    String::new()
+                } else {
+                    online.stddev().to_string()
+                }
+            }
+            Aggregator::First(ref v) | Aggregator::Last(ref v) => {
+                v.as_ref().map_or_else(String::new, |v| lossy(v))
+            }
+            Aggregator::CountDistinct(ref set) => set.len().to_string(),
+            Aggregator::Concat(ref values) => {
+                let values: Vec<String> =
+                    values.iter().map(|v| lossy(v)).collect();
+                values.join(separator)
+            }
+        }
+    }
+}
+
+impl Commute for Aggregator {
+    fn merge(&mut self, other: Aggregator) {
+        match (self, other) {
+            (&mut Aggregator::Count(ref mut n1), Aggregator::Count(n2)) => {
+                *n1 += n2;
+            }
+            (&mut Aggregator::Sum(ref mut t1, ref mut s1),
+             Aggregator::Sum(t2, s2)) => {
+                t1.merge(t2);
+                s1.merge(s2);
+            }
+            (&mut Aggregator::Min(ref mut t1, ref mut m1),
+             Aggregator::Min(t2, m2))
+            | (&mut Aggregator::Max(ref mut t1, ref mut m1),
+               Aggregator::Max(t2, m2)) => {
+                t1.merge(t2);
+                m1.merge(m2);
+            }
+            (&mut Aggregator::Mean(ref mut o1), Aggregator::Mean(o2))
+            | (&mut Aggregator::Stddev(ref mut o1),
+               Aggregator::Stddev(o2)) => {
+                o1.merge(o2);
+            }
+            (&mut Aggregator::First(ref mut v1), Aggregator::First(v2)) => {
+                if v1.is_none() {
+                    *v1 = v2;
+                }
+            }
+            (&mut Aggregator::Last(ref mut v1), Aggregator::Last(v2)) => {
+                if v2.is_some() {
+                    *v1 = v2;
+                }
+            }
+            (&mut Aggregator::CountDistinct(ref mut s1),
+             Aggregator::CountDistinct(s2)) => {
+                s1.extend(s2);
+            }
+            (&mut Aggregator::Concat(ref mut v1), Aggregator::Concat(v2)) => {
+                v1.extend(v2);
+            }
+            _ => unreachable!("cannot merge different aggregators"),
+        }
+    }
+}
+
+fn from_bytes(bytes: &[u8]) -> Option<f64> {
+    ::std::str::from_utf8(bytes).ok().and_then(|s| s.parse().ok())
+}
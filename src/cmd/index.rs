@@ -6,6 +6,8 @@ use csv_index::RandomAccessSimple;
 
 use CliResult;
 use config::{Config, Delimiter};
+use index::create_value_index;
+use select::SelectColumns;
 use util;
 
 static USAGE: &'static str = "
@@ -18,6 +20,16 @@ automatically used by commands that can benefit from it. If the original CSV
 data changes after the index is made, commands that try to use it will result
 in an error (you have to regenerate the index before it can be used again).
 
+With --columns, a value index is created instead. It sorts the rows by the
+values of the given columns, so that the rows with given values can be found
+with a binary search instead of parsing all of the CSV data. It is created at
+'path/to/input.csv.<columns>.vidx', where <columns> are the indices of the
+columns (starting at 1) joined by '-', e.g., 'input.csv.1-3.vidx'. It is used
+automatically by 'xsv join' for the second input when joining on the same
+columns, by 'xsv slice --key' and by 'xsv search' on the same columns. Like
+the index above, it must be regenerated when the CSV data changes. Several
+value indexes for different columns can exist at the same time.
+
 Usage:
     xsv index [options] <input>
     xsv index --help
@@ -27,9 +39,13 @@ index options:
                            Generally, this is not currently useful because
                            the only way to use an index is if it is specially
                            named <input>.idx.
+    -c, --columns <arg>    Create a value index of the given columns.
+                           See 'xsv select --help' for the format details.
 
 Common options:
     -h, --help             Display this message
+    -n, --no-headers       When set, the first row will not be interpreted
+                           as headers, so --columns must use indices.
     -d, --delimiter <arg>  The field delimiter for reading CSV data.
                            Must be a single character. (default: ,)
 ";
@@ -38,11 +54,16 @@ Common options:
 struct Args {
     arg_input: String,
     flag_output: Option<String>,
+    flag_columns: Option<SelectColumns>,
+    flag_no_headers: bool,
     flag_delimiter: Option<Delimiter>,
 }
 
 pub fn run(argv: &[&str]) -> CliResult<()> {
     let args: Args = util::get_args(USAGE, argv)?;
+    if let Some(ref columns) = args.flag_columns {
+        return value_index(&args, columns);
+    }
 
     let pidx = match args.flag_output {
         None => util::idx_path(&Path::new(&args.arg_input)),
@@ -56,3 +77,21 @@ pub fn run(argv: &[&str]) -> CliResult<()> {
     RandomAccessSimple::create(&mut rdr, &mut wtr)?;
     Ok(())
 }
+
+fn value_index(args: &Args, columns: &SelectColumns) -> CliResult<()> {
+    let rconfig = Config::new(&Some(args.arg_input.clone()))
+                         .delimiter(args.flag_delimiter)
+                         .no_headers(args.flag_no_headers)
+                         .select(columns.clone());
+    let sel = rconfig.selection(rconfig.reader_file()?.byte_headers()?)?;
+    let pidx = match args.flag_output {
+        None => util::vidx_path(&Path::new(&args.arg_input), &sel),
+        Some(ref p) => PathBuf::from(p),
+    };
+
+    // Every row is indexed, including the header row, so that the index
+    // can be used whether or not the data is read with headers.
+    let mut rdr = rconfig.no_headers(true).reader_file()?;
+    let wtr = io::BufWriter::new(fs::File::create(&pidx)?);
+    create_value_index(&mut rdr, &sel, wtr)
+}
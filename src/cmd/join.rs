@@ -1,28 +1,31 @@
+use std::cmp::Ordering;
 use std::collections::hash_map::{HashMap, Entry};
 use std::fmt;
 use std::fs;
 use std::io;
 use std::iter::repeat;
-use std::str;
 
-use byteorder::{WriteBytesExt, BigEndian};
+use chrono::NaiveDateTime;
 use csv;
 
 use CliResult;
+use cmd::stats::Formats;
 use config::{Config, Delimiter};
-use index::Indexed;
+use index::ValueIndexReader;
+use normalize::KeyNormalizer;
 use select::{SelectColumns, Selection};
-use util;
+use util::{self, ByteString};
 
 static USAGE: &'static str = "
-Joins two sets of CSV data on the specified columns.
+Joins two (or more) sets of CSV data on the specified columns.
 
 The default join operation is an 'inner' join. This corresponds to the
 intersection of rows on the keys specified.
 
-Joins are always done by ignoring leading and trailing whitespace. By default,
-joins are done case sensitively, but this can be disabled with the --no-case
-flag.
+Joins are always done by ignoring leading and trailing whitespace, unless the
+inputs are merged with --merge. By default, joins are done case sensitively,
+but this can be disabled with the --no-case flag. Keys can be normalized
+further with --normalize and --key-regex.
 
 The columns arguments specify the columns to join for each input. Columns can
 be referenced by name or index, starting at 1. Specify multiple columns by
@@ -30,12 +33,49 @@ separating them with a comma. Specify a range of columns with `-`. Both
 columns1 and columns2 must specify exactly the same number of columns.
 (See 'xsv select --help' for the full syntax.)
 
+More than two inputs can be joined by giving more pairs of columns and
+inputs. The rows of the first input are then matched against each of the
+other inputs in turn, on <columns1> and the columns given for that input.
+Only inner and left joins are supported with more than two inputs. Columns
+with the same name can be told apart with the --prefix and --suffix options.
+
+Joins other than --cross, --merge, --asof and --interval build an index of the
+second input (and of any later inputs) in memory. When such an input has a
+value index of its join columns, made with 'xsv index --columns', it is read
+from there instead of from the CSV data.
+
+The --asof and --interval joins match rows by order instead of equality on
+the last of the join columns. Any other join columns are still matched by
+equality, which makes it possible to, e.g., join trades with the latest quote
+of the same symbol. Values in the ordered column are compared as numbers when
+both are numbers, as points in time when both are dates or date times that
+'xsv stats' recognizes (date times with a UTC offset are converted to UTC),
+and as text otherwise. Rows with an empty value in the ordered column never
+match.
+
 Usage:
-    xsv join [options] <columns1> <input1> <columns2> <input2>
+    xsv join [options] <columns1> <input1> <columns2> <input2> [<columns> <input>]...
     xsv join --help
 
 join options:
     --no-case              When set, joins are done case insensitively.
+    --normalize <list>     A comma separated list of normalizers applied in
+                           order to the keys of both inputs before they are
+                           compared. The normalizers are:
+                             trim      remove surrounding whitespace
+                             lower     lowercase
+                             nfc       Unicode normalization form C
+                             nfkc      Unicode normalization form KC
+                             unaccent  remove accents and other marks
+                             alnum     remove all but letters and digits
+                             numeric   compare finite numbers by value,
+                                       e.g., '007' and '7.0' are equal
+                                       to '7'
+    --key-regex <regex>    Only compare the part of each key field matched
+                           by <regex>. When the regex has a capture group,
+                           the first group is used instead. Fields that
+                           don't match are treated as empty. This applies
+                           before --normalize.
     --left                 Do a 'left outer' join. This returns all rows in
                            first CSV data set, including rows with no
                            corresponding row in the second data set. When no
@@ -51,6 +91,28 @@ join options:
                            there is no match, the missing side will be padded
                            out with empty fields. (This is the combination of
                            'outer left' and 'outer right'.)
+    --left-semi            Do a 'left semi' join. This returns the rows of
+                           the first CSV data set that have at least one
+                           corresponding row in the second data set. Only
+                           the columns of the first data set are written and
+                           each row is written at most once.
+    --left-anti            Do a 'left anti' join. This returns the rows of
+                           the first CSV data set that have no corresponding
+                           row in the second data set. Only the columns of
+                           the first data set are written.
+    --asof                 Do an 'as-of' join. Each row of the first data set
+                           is joined with the row of the second data set that
+                           has the latest value at or before its own in the
+                           last join column. Only inner and left joins are
+                           supported.
+    --interval             Do an interval join. The second column selection
+                           must have one more column than the first, its
+                           last two columns holding the start and end of an
+                           interval. Each row of the first data set is joined
+                           with every row of the second data set whose
+                           interval contains the value in its last join
+                           column, i.e., where start <= value < end. Only
+                           inner and left joins are supported.
     --cross                USE WITH CAUTION.
                            This returns the cartesian product of the CSV
                            data sets given. The number of rows return is
@@ -60,6 +122,25 @@ join options:
                            Otherwise, empty fields are completely ignored.
                            (In fact, any row that has an empty field in the
                            key specified is ignored.)
+    --prefix <names>       A comma separated list of prefixes, one for each
+                           input, that are added to the header names of
+                           that input in the output, e.g., 'a_,b_'. Leave an
+                           entry empty to keep the names of an input as is.
+    --suffix <names>       Like --prefix, but adds suffixes instead.
+    --merge                Join by merging the two inputs in a single pass
+                           instead of indexing the second input in memory.
+                           This uses constant memory (apart from rows that
+                           share the same key), but both inputs must already
+                           be sorted lexicographically on their join columns,
+                           e.g., with 'xsv sort --select <columns>'. Keys are
+                           compared exactly as 'xsv sort' orders them, so
+                           whitespace is not trimmed. With --no-case, sort
+                           the inputs with '--order nocase'. Likewise, sort
+                           them with the same --normalize and --key-regex
+                           options that are given here. An error is
+                           reported if an input turns out not to be sorted.
+                           This works with inner, left, right and full
+                           joins.
 
 Common options:
     -h, --help             Display this message
@@ -71,51 +152,108 @@ Common options:
                            Must be a single character. (default: ,)
 ";
 
-type ByteString = Vec<u8>;
-
 #[derive(Deserialize)]
 struct Args {
     arg_columns1: SelectColumns,
     arg_input1: String,
     arg_columns2: SelectColumns,
     arg_input2: String,
+    arg_columns: Vec<SelectColumns>,
+    arg_input: Vec<String>,
     flag_left: bool,
     flag_right: bool,
     flag_full: bool,
+    flag_left_semi: bool,
+    flag_left_anti: bool,
+    flag_asof: bool,
+    flag_interval: bool,
     flag_cross: bool,
     flag_output: Option<String>,
     flag_no_headers: bool,
     flag_no_case: bool,
+    flag_normalize: Option<String>,
+    flag_key_regex: Option<String>,
     flag_nulls: bool,
+    flag_merge: bool,
+    flag_prefix: Option<String>,
+    flag_suffix: Option<String>,
     flag_delimiter: Option<Delimiter>,
 }
 
 pub fn run(argv: &[&str]) -> CliResult<()> {
     let args: Args = util::get_args(USAGE, argv)?;
+    if args.flag_asof || args.flag_interval {
+        if args.flag_asof && args.flag_interval {
+            return fail!("Please pick at most one of --asof and --interval.");
+        }
+        if !args.arg_input.is_empty() || args.flag_right || args.flag_full
+            || args.flag_cross || args.flag_left_semi || args.flag_left_anti
+            || args.flag_merge {
+            return fail!("--asof and --interval only support inner and left \
+                          joins of two inputs.");
+        }
+        let mut state = args.new_io_state()?;
+        state.write_headers()?;
+        return state.range_join(args.flag_interval, args.flag_left);
+    }
     let mut state = args.new_io_state()?;
+    if !state.extra.is_empty() {
+        if args.flag_right || args.flag_full || args.flag_cross
+            || args.flag_left_semi || args.flag_left_anti || args.flag_merge {
+            return fail!("Only inner and left joins are supported with \
+                          more than two inputs.");
+        }
+        state.write_headers()?;
+        return state.chain_join(args.flag_left);
+    }
+    if args.flag_merge {
+        if args.flag_cross || args.flag_left_semi || args.flag_left_anti {
+            return fail!("--merge cannot be used with --cross, --left-semi \
+                          or --left-anti.");
+        }
+        if [args.flag_left, args.flag_right, args.flag_full]
+                .iter().filter(|&&b| b).count() > 1 {
+            return fail!("Please pick exactly one join operation.");
+        }
+        state.write_headers()?;
+        return state.merge_join(
+            args.flag_left || args.flag_full,
+            args.flag_right || args.flag_full,
+        );
+    }
     match (
         args.flag_left,
         args.flag_right,
         args.flag_full,
         args.flag_cross,
+        args.flag_left_semi,
+        args.flag_left_anti,
     ) {
-        (true, false, false, false) => {
+        (true, false, false, false, false, false) => {
             state.write_headers()?;
             state.outer_join(false)
         }
-        (false, true, false, false) => {
+        (false, true, false, false, false, false) => {
             state.write_headers()?;
             state.outer_join(true)
         }
-        (false, false, true, false) => {
+        (false, false, true, false, false, false) => {
             state.write_headers()?;
             state.full_outer_join()
         }
-        (false, false, false, true) => {
+        (false, false, false, true, false, false) => {
             state.write_headers()?;
             state.cross_join()
         }
-        (false, false, false, false) => {
+        (false, false, false, false, true, false) => {
+            state.write_headers1()?;
+            state.semi_join(false)
+        }
+        (false, false, false, false, false, true) => {
+            state.write_headers1()?;
+            state.semi_join(true)
+        }
+        (false, false, false, false, false, false) => {
             state.write_headers()?;
             state.inner_join()
         }
@@ -129,39 +267,73 @@ struct IoState<R, W: io::Write> {
     sel1: Selection,
     rdr2: csv::Reader<R>,
     sel2: Selection,
+    /// Inputs beyond the second one, in order.
+    extra: Vec<(csv::Reader<R>, Selection)>,
+    /// The value index files made with `xsv index --columns` for the join
+    /// columns of each input, when there are any.
+    vidxs: Vec<Option<fs::File>>,
+    /// Prefixes and suffixes for the header names of each input. These
+    /// are empty when not given.
+    prefixes: Vec<String>,
+    suffixes: Vec<String>,
     no_headers: bool,
-    casei: bool,
+    norm: KeyNormalizer,
     nulls: bool,
 }
 
 impl<R: io::Read + io::Seek, W: io::Write> IoState<R, W> {
     fn write_headers(&mut self) -> CliResult<()> {
         if !self.no_headers {
-            let mut headers = self.rdr1.byte_headers()?.clone();
-            headers.extend(self.rdr2.byte_headers()?.iter());
+            let headers1 = self.rdr1.byte_headers()?.clone();
+            let headers2 = self.rdr2.byte_headers()?.clone();
+            let mut headers = self.affix_headers(0, &headers1);
+            headers.extend(self.affix_headers(1, &headers2).iter());
+            for i in 0..self.extra.len() {
+                let headers_n = self.extra[i].0.byte_headers()?.clone();
+                headers.extend(self.affix_headers(i + 2, &headers_n).iter());
+            }
+            self.wtr.write_record(&headers)?;
+        }
+        Ok(())
+    }
+
+    fn write_headers1(&mut self) -> CliResult<()> {
+        if !self.no_headers {
+            let headers1 = self.rdr1.byte_headers()?.clone();
+            let headers = self.affix_headers(0, &headers1);
             self.wtr.write_record(&headers)?;
         }
         Ok(())
     }
 
+    /// Add the prefix and suffix of the `i`th input to its header names.
+    fn affix_headers(
+        &self,
+        i: usize,
+        headers: &csv::ByteRecord,
+    ) -> csv::ByteRecord {
+        let prefix = self.prefixes.get(i).map_or("", |s| &**s);
+        let suffix = self.suffixes.get(i).map_or("", |s| &**s);
+        headers.iter().map(|name| {
+            let mut affixed = prefix.as_bytes().to_vec();
+            affixed.extend_from_slice(name);
+            affixed.extend_from_slice(suffix.as_bytes());
+            affixed
+        }).collect()
+    }
+
     fn inner_join(mut self) -> CliResult<()> {
         let mut scratch = csv::ByteRecord::new();
         let mut validx = ValueIndex::new(
-            self.rdr2, &self.sel2, self.casei, self.nulls)?;
+            self.rdr2, &self.sel2, self.vidxs[1].take(), &self.norm,
+            self.nulls)?;
         for row in self.rdr1.byte_records() {
             let row = row?;
-            let key = get_row_key(&self.sel1, &row, self.casei);
-            match validx.values.get(&key) {
-                None => continue,
-                Some(rows) => {
-                    for &rowi in rows.iter() {
-                        validx.idx.seek(rowi as u64)?;
-
-                        validx.idx.read_byte_record(&mut scratch)?;
-                        let combined = row.iter().chain(scratch.iter());
-                        self.wtr.write_record(combined)?;
-                    }
-                }
+            let key = get_row_key(&self.sel1, &row, &self.norm);
+            for rowi in validx.rows(&key)? {
+                validx.read_row(rowi, &mut scratch)?;
+                let combined = row.iter().chain(scratch.iter());
+                self.wtr.write_record(combined)?;
             }
         }
         Ok(())
@@ -171,34 +343,32 @@ impl<R: io::Read + io::Seek, W: io::Write> IoState<R, W> {
         if right {
             ::std::mem::swap(&mut self.rdr1, &mut self.rdr2);
             ::std::mem::swap(&mut self.sel1, &mut self.sel2);
+            self.vidxs.swap(0, 1);
         }
 
         let mut scratch = csv::ByteRecord::new();
         let (_, pad2) = self.get_padding()?;
         let mut validx = ValueIndex::new(
-            self.rdr2, &self.sel2, self.casei, self.nulls)?;
+            self.rdr2, &self.sel2, self.vidxs[1].take(), &self.norm,
+            self.nulls)?;
         for row in self.rdr1.byte_records() {
             let row = row?;
-            let key = get_row_key(&self.sel1, &row, self.casei);
-            match validx.values.get(&key) {
-                None => {
-                    if right {
-                        self.wtr.write_record(pad2.iter().chain(&row))?;
-                    } else {
-                        self.wtr.write_record(row.iter().chain(&pad2))?;
-                    }
+            let key = get_row_key(&self.sel1, &row, &self.norm);
+            let rows = validx.rows(&key)?;
+            if rows.is_empty() {
+                if right {
+                    self.wtr.write_record(pad2.iter().chain(&row))?;
+                } else {
+                    self.wtr.write_record(row.iter().chain(&pad2))?;
                 }
-                Some(rows) => {
-                    for &rowi in rows.iter() {
-                        validx.idx.seek(rowi as u64)?;
-                        let row1 = row.iter();
-                        validx.idx.read_byte_record(&mut scratch)?;
-                        if right {
-                            self.wtr.write_record(scratch.iter().chain(row1))?;
-                        } else {
-                            self.wtr.write_record(row1.chain(&scratch))?;
-                        }
-                    }
+            }
+            for rowi in rows {
+                validx.read_row(rowi, &mut scratch)?;
+                let row1 = row.iter();
+                if right {
+                    self.wtr.write_record(scratch.iter().chain(row1))?;
+                } else {
+                    self.wtr.write_record(row1.chain(&scratch))?;
                 }
             }
         }
@@ -209,27 +379,24 @@ impl<R: io::Read + io::Seek, W: io::Write> IoState<R, W> {
         let mut scratch = csv::ByteRecord::new();
         let (pad1, pad2) = self.get_padding()?;
         let mut validx = ValueIndex::new(
-            self.rdr2, &self.sel2, self.casei, self.nulls)?;
+            self.rdr2, &self.sel2, self.vidxs[1].take(), &self.norm,
+            self.nulls)?;
 
         // Keep track of which rows we've written from rdr2.
         let mut rdr2_written: Vec<_> =
             repeat(false).take(validx.num_rows).collect();
         for row1 in self.rdr1.byte_records() {
             let row1 = row1?;
-            let key = get_row_key(&self.sel1, &row1, self.casei);
-            match validx.values.get(&key) {
-                None => {
-                    self.wtr.write_record(row1.iter().chain(&pad2))?;
-                }
-                Some(rows) => {
-                    for &rowi in rows.iter() {
-                        rdr2_written[rowi] = true;
+            let key = get_row_key(&self.sel1, &row1, &self.norm);
+            let rows = validx.rows(&key)?;
+            if rows.is_empty() {
+                self.wtr.write_record(row1.iter().chain(&pad2))?;
+            }
+            for rowi in rows {
+                rdr2_written[rowi] = true;
 
-                        validx.idx.seek(rowi as u64)?;
-                        validx.idx.read_byte_record(&mut scratch)?;
-                        self.wtr.write_record(row1.iter().chain(&scratch))?;
-                    }
-                }
+                validx.read_row(rowi, &mut scratch)?;
+                self.wtr.write_record(row1.iter().chain(&scratch))?;
             }
         }
 
@@ -237,14 +404,129 @@ impl<R: io::Read + io::Seek, W: io::Write> IoState<R, W> {
         // from rdr1.
         for (i, &written) in rdr2_written.iter().enumerate() {
             if !written {
-                validx.idx.seek(i as u64)?;
-                validx.idx.read_byte_record(&mut scratch)?;
+                validx.read_row(i, &mut scratch)?;
                 self.wtr.write_record(pad1.iter().chain(&scratch))?;
             }
         }
         Ok(())
     }
 
+    /// Join the first input with every other input in turn, using one
+    /// value index for each of them.
+    ///
+    /// Each row of the first input is written once for every combination
+    /// of matching rows from the other inputs. When `left` is set, an input
+    /// without a matching row contributes a padded row instead.
+    fn chain_join(mut self, left: bool) -> CliResult<()> {
+        let mut sides = vec![(self.rdr2, self.sel2)];
+        sides.extend(self.extra);
+
+        let (mut validxs, mut pads) = (vec![], vec![]);
+        let vidxs = self.vidxs.drain(1..).collect::<Vec<_>>();
+        for ((mut rdr, sel), vidx) in sides.into_iter().zip(vidxs) {
+            let len = rdr.byte_headers()?.len();
+            pads.push(repeat(b"").take(len).collect::<csv::ByteRecord>());
+            validxs.push(
+                ValueIndex::new(rdr, &sel, vidx, &self.norm, self.nulls)?);
+        }
+
+        let mut scratch = csv::ByteRecord::new();
+        'ROWS: for row in self.rdr1.byte_records() {
+            let row = row?;
+            let key = get_row_key(&self.sel1, &row, &self.norm);
+            let mut matches = Vec::with_capacity(validxs.len());
+            for (validx, pad) in validxs.iter_mut().zip(&pads) {
+                let mut rows = vec![];
+                for rowi in validx.rows(&key)? {
+                    validx.read_row(rowi, &mut scratch)?;
+                    rows.push(scratch.clone());
+                }
+                if rows.is_empty() {
+                    if !left {
+                        continue 'ROWS;
+                    }
+                    rows.push(pad.clone());
+                }
+                matches.push(rows);
+            }
+
+            // Write the cartesian product of the matching rows, iterating
+            // over the last input fastest.
+            let mut pos = vec![0; matches.len()];
+            loop {
+                let mut combined = row.clone();
+                for (rows, &i) in matches.iter().zip(&pos) {
+                    combined.extend(rows[i].iter());
+                }
+                self.wtr.write_record(&combined)?;
+
+                let mut k = matches.len();
+                loop {
+                    if k == 0 {
+                        continue 'ROWS;
+                    }
+                    k -= 1;
+                    pos[k] += 1;
+                    if pos[k] < matches[k].len() {
+                        break;
+                    }
+                    pos[k] = 0;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Write the rows of the first input that have a match in the second
+    /// input (or, when `anti` is set, the rows that don't).
+    fn semi_join(mut self, anti: bool) -> CliResult<()> {
+        let mut validx = ValueIndex::new(
+            self.rdr2, &self.sel2, self.vidxs[1].take(), &self.norm,
+            self.nulls)?;
+        for row in self.rdr1.byte_records() {
+            let row = row?;
+            let key = get_row_key(&self.sel1, &row, &self.norm);
+            if validx.rows(&key)?.is_empty() == anti {
+                self.wtr.write_record(&row)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Join each row of the first input with the row of the second input
+    /// that has the latest ordered key at or before its own or, when
+    /// `interval` is set, with every row whose interval contains it.
+    ///
+    /// The ordered key is the last join column of the first input. Any
+    /// join columns before it must be equal, as in the other joins.
+    fn range_join(mut self, interval: bool, left: bool) -> CliResult<()> {
+        let mut scratch = csv::ByteRecord::new();
+        let (_, pad2) = self.get_padding()?;
+        let nby = self.sel1.len() - 1;
+        let formats = Formats::default();
+        let mut ranges = RangeIndex::new(
+            self.rdr2, &self.sel2, nby, interval, &self.norm, &formats,
+            self.nulls)?;
+        for row in self.rdr1.byte_records() {
+            let row = row?;
+            let (by, at) =
+                range_key(&self.sel1, nby, &row, &self.norm, &formats);
+            let null = !self.nulls && by.iter().any(|f| f.is_empty());
+            let found = match at {
+                Some(ref at) if !null => ranges.find(&by, at, interval),
+                _ => vec![],
+            };
+            if found.is_empty() && left {
+                self.wtr.write_record(row.iter().chain(&pad2))?;
+            }
+            for pos in found {
+                ranges.read(pos, &mut scratch)?;
+                self.wtr.write_record(row.iter().chain(&scratch))?;
+            }
+        }
+        Ok(())
+    }
+
     fn cross_join(mut self) -> CliResult<()> {
         let mut pos = csv::Position::new();
         pos.set_byte(0);
@@ -264,6 +546,67 @@ impl<R: io::Read + io::Seek, W: io::Write> IoState<R, W> {
         Ok(())
     }
 
+    /// Join two inputs that are sorted on their join keys by walking
+    /// through both of them at the same time.
+    ///
+    /// Rows of the second input are grouped by key, so only the rows that
+    /// share the current key are kept in memory. When `left` (`right`) is
+    /// set, rows of the first (second) input without a match are written
+    /// with padding, like `outer_join` does.
+    fn merge_join(mut self, left: bool, right: bool) -> CliResult<()> {
+        let (pad1, pad2) = self.get_padding()?;
+        let mut input1 = SortedInput::new(&mut self.rdr1, &self.sel1, 1);
+        let mut input2 = SortedInput::new(&mut self.rdr2, &self.sel2, 2);
+        let (norm, nulls) = (&self.norm, self.nulls);
+
+        let mut row1 = input1.next(norm)?;
+        let mut group2 = input2.next_group(norm)?;
+        let mut group2_matched = false;
+        loop {
+            let ord = match (&row1, &group2) {
+                (&None, &None) => break,
+                (&Some(_), &None) => Ordering::Less,
+                (&None, &Some(_)) => Ordering::Greater,
+                (&Some((ref key1, _)), &Some((ref key2, _))) => {
+                    key1.cmp(key2)
+                }
+            };
+            match ord {
+                Ordering::Less => {
+                    let (_, row) = row1.take().unwrap();
+                    if left {
+                        self.wtr.write_record(row.iter().chain(&pad2))?;
+                    }
+                    row1 = input1.next(norm)?;
+                }
+                Ordering::Greater => {
+                    let (_, rows) = group2.take().unwrap();
+                    if right && !group2_matched {
+                        for row in &rows {
+                            self.wtr.write_record(pad1.iter().chain(row))?;
+                        }
+                    }
+                    group2 = input2.next_group(norm)?;
+                    group2_matched = false;
+                }
+                Ordering::Equal => {
+                    let (key, row) = row1.take().unwrap();
+                    if nulls || !key.iter().any(|f| f.is_empty()) {
+                        let rows = &group2.as_ref().unwrap().1;
+                        for row2 in rows {
+                            self.wtr.write_record(row.iter().chain(row2))?;
+                        }
+                        group2_matched = true;
+                    } else if left {
+                        self.wtr.write_record(row.iter().chain(&pad2))?;
+                    }
+                    row1 = input1.next(norm)?;
+                }
+            }
+        }
+        Ok(())
+    }
+
     fn get_padding(
         &mut self,
     ) -> CliResult<(csv::ByteRecord, csv::ByteRecord)> {
@@ -279,31 +622,70 @@ impl<R: io::Read + io::Seek, W: io::Write> IoState<R, W> {
 impl Args {
     fn new_io_state(&self)
         -> CliResult<IoState<fs::File, Box<io::Write+'static>>> {
-        let rconf1 = Config::new(&Some(self.arg_input1.clone()))
-            .delimiter(self.flag_delimiter)
-            .no_headers(self.flag_no_headers)
-            .select(self.arg_columns1.clone());
-        let rconf2 = Config::new(&Some(self.arg_input2.clone()))
-            .delimiter(self.flag_delimiter)
-            .no_headers(self.flag_no_headers)
-            .select(self.arg_columns2.clone());
+        let rconf1 = self.rconfig(&self.arg_input1, &self.arg_columns1);
+        let rconf2 = self.rconfig(&self.arg_input2, &self.arg_columns2);
 
         let mut rdr1 = rconf1.reader_file()?;
         let mut rdr2 = rconf2.reader_file()?;
         let (sel1, sel2) = self.get_selections(
             &rconf1, &mut rdr1, &rconf2, &mut rdr2)?;
+
+        let mut vidxs = vec![
+            rconf1.value_index_file(&sel1)?,
+            rconf2.value_index_file(&sel2)?,
+        ];
+        let mut extra = vec![];
+        let pairs = self.arg_columns.iter().zip(self.arg_input.iter());
+        for (columns, input) in pairs {
+            let rconf = self.rconfig(input, columns);
+            let mut rdr = rconf.reader_file()?;
+            let (_, sel) = self.get_selections(
+                &rconf1, &mut rdr1, &rconf, &mut rdr)?;
+            vidxs.push(rconf.value_index_file(&sel)?);
+            extra.push((rdr, sel));
+        }
+
+        let ninputs = 2 + extra.len();
         Ok(IoState {
             wtr: Config::new(&self.flag_output).writer()?,
             rdr1: rdr1,
             sel1: sel1,
             rdr2: rdr2,
             sel2: sel2,
+            extra: extra,
+            vidxs: vidxs,
+            prefixes: affixes("--prefix", &self.flag_prefix, ninputs)?,
+            suffixes: affixes("--suffix", &self.flag_suffix, ninputs)?,
             no_headers: rconf1.no_headers,
-            casei: self.flag_no_case,
+            norm: self.normalizer()?,
             nulls: self.flag_nulls,
         })
     }
 
+    fn normalizer(&self) -> CliResult<KeyNormalizer> {
+        if self.flag_merge {
+            // Keys must be in the order that 'xsv sort' puts them in, which
+            // is never trimmed. It applies the key regex and normalizers
+            // first, and then lowercases with '--order nocase'.
+            return Ok(KeyNormalizer::new()
+                .steps(&self.flag_normalize)?
+                .lowercase(self.flag_no_case)
+                .regex(&self.flag_key_regex)?);
+        }
+        Ok(KeyNormalizer::new()
+            .trim(true)
+            .lowercase(self.flag_no_case)
+            .steps(&self.flag_normalize)?
+            .regex(&self.flag_key_regex)?)
+    }
+
+    fn rconfig(&self, input: &str, columns: &SelectColumns) -> Config {
+        Config::new(&Some(input.to_owned()))
+            .delimiter(self.flag_delimiter)
+            .no_headers(self.flag_no_headers)
+            .select(columns.clone())
+    }
+
     fn get_selections<R: io::Read>(
         &self,
         rconf1: &Config, rdr1: &mut csv::Reader<R>,
@@ -313,7 +695,14 @@ impl Args {
         let headers2 = rdr2.byte_headers()?;
         let select1 = rconf1.selection(&*headers1)?;
         let select2 = rconf2.selection(&*headers2)?;
-        if select1.len() != select2.len() {
+        if self.flag_interval && select1.len() + 1 != select2.len() {
+            return fail!(format!(
+                "With --interval, the second column selection must have one \
+                 more column than the first (for the start and end of the \
+                 intervals), but found column selections with {} and {} \
+                 columns.", select1.len(), select2.len()));
+        }
+        if !self.flag_interval && select1.len() != select2.len() {
             return fail!(format!(
                 "Column selections must have the same number of columns, \
                  but found column selections with {} and {} columns.",
@@ -323,82 +712,271 @@ impl Args {
     }
 }
 
+/// Split a list of header affixes given on the command line, checking that
+/// there is one for every input.
+fn affixes(
+    flag: &str,
+    list: &Option<String>,
+    ninputs: usize,
+) -> CliResult<Vec<String>> {
+    let list = match *list {
+        None => return Ok(vec![]),
+        Some(ref list) => list,
+    };
+    let affixes: Vec<String> = list.split(',').map(|s| s.to_owned()).collect();
+    if affixes.len() != ninputs {
+        return fail!(format!(
+            "{} must have one entry for each of the {} inputs, but it has {}.",
+            flag, ninputs, affixes.len()));
+    }
+    Ok(affixes)
+}
+
+/// SortedInput reads rows from an input that is sorted on its join key,
+/// and reports an error if it turns out that it isn't.
+struct SortedInput<'a, R: 'a> {
+    rdr: &'a mut csv::Reader<R>,
+    sel: &'a Selection,
+    /// The position of this input on the command line, for error messages.
+    which: usize,
+    last: Option<Vec<ByteString>>,
+    peeked: Option<(Vec<ByteString>, csv::ByteRecord)>,
+}
+
+impl<'a, R: io::Read> SortedInput<'a, R> {
+    fn new(
+        rdr: &'a mut csv::Reader<R>,
+        sel: &'a Selection,
+        which: usize,
+    ) -> SortedInput<'a, R> {
+        SortedInput {
+            rdr: rdr,
+            sel: sel,
+            which: which,
+            last: None,
+            peeked: None,
+        }
+    }
+
+    /// Return the next row along with its key.
+    fn next(
+        &mut self,
+        norm: &KeyNormalizer,
+    ) -> CliResult<Option<(Vec<ByteString>, csv::ByteRecord)>> {
+        if let Some(peeked) = self.peeked.take() {
+            return Ok(Some(peeked));
+        }
+        let mut row = csv::ByteRecord::new();
+        if !self.rdr.read_byte_record(&mut row)? {
+            return Ok(None);
+        }
+        let key = get_row_key(self.sel, &row, norm);
+        if let Some(ref last) = self.last {
+            if key < *last {
+                return fail!(format!(
+                    "Input {} is not sorted on its join columns: found \
+                     key {:?} after {:?} (at {}).",
+                    self.which, show_key(&key), show_key(last),
+                    row.position().map_or(
+                        "unknown position".to_owned(),
+                        |p| format!("line {}", p.line()))));
+            }
+        }
+        self.last = Some(key.clone());
+        Ok(Some((key, row)))
+    }
+
+    /// Return the next key along with all of the rows that have it.
+    fn next_group(
+        &mut self,
+        norm: &KeyNormalizer,
+    ) -> CliResult<Option<(Vec<ByteString>, Vec<csv::ByteRecord>)>> {
+        let (key, first) = match self.next(norm)? {
+            None => return Ok(None),
+            Some(next) => next,
+        };
+        let mut rows = vec![first];
+        while let Some((k, row)) = self.next(norm)? {
+            if k != key {
+                self.peeked = Some((k, row));
+                break;
+            }
+            rows.push(row);
+        }
+        Ok(Some((key, rows)))
+    }
+}
+
+fn show_key(key: &[ByteString]) -> Vec<String> {
+    key.iter().map(|k| String::from_utf8_lossy(k).into_owned()).collect()
+}
+
 struct ValueIndex<R> {
-    // This maps tuples of values to corresponding rows.
-    values: HashMap<Vec<ByteString>, Vec<usize>>,
-    idx: Indexed<R, io::Cursor<Vec<u8>>>,
+    rdr: csv::Reader<R>,
+    lookup: Lookup,
+    // The record number of the first row, which is 1 if there is a header
+    // row.
+    first: u64,
     num_rows: usize,
+    nulls: bool,
+}
+
+/// Lookup finds the rows that have a given key, either with a map built in
+/// memory or by searching a value index file.
+enum Lookup {
+    Memory {
+        // This maps tuples of values to corresponding rows.
+        values: HashMap<Vec<ByteString>, Vec<usize>>,
+        // The byte offset of each row.
+        offsets: Vec<u64>,
+    },
+    File(ValueIndexReader<io::BufReader<fs::File>>),
 }
 
 impl<R: io::Read + io::Seek> ValueIndex<R> {
+    /// Index the rows of `rdr` by the values of the columns in `sel`.
+    ///
+    /// When there is a value index file and keys are only trimmed, rows are
+    /// looked up in the file directly. Otherwise, the keys in the file are
+    /// normalized into a map, which avoids parsing the CSV data at least.
     fn new(
         mut rdr: csv::Reader<R>,
         sel: &Selection,
-        casei: bool,
+        vidx: Option<fs::File>,
+        norm: &KeyNormalizer,
         nulls: bool,
     ) -> CliResult<ValueIndex<R>> {
-        let mut val_idx = HashMap::with_capacity(10000);
-        let mut row_idx = io::Cursor::new(Vec::with_capacity(8 * 10000));
-        let (mut rowi, mut count) = (0usize, 0usize);
+        let first = if rdr.has_headers() { 1 } else { 0 };
+        if let Some(vidx) = vidx {
+            let mut vidx = ValueIndexReader::new(io::BufReader::new(vidx))?;
+            let num_rows = vidx.count().saturating_sub(first) as usize;
+            let lookup = if norm.is_trim_only() {
+                Lookup::File(vidx)
+            } else {
+                let mut entries = vidx.entries()?;
+                entries.sort_by_key(|&(n, _)| n);
+                let mut values = HashMap::with_capacity(entries.len());
+                for (n, key) in entries {
+                    if n < first {
+                        continue;
+                    }
+                    let fields = key.iter().map(|v| norm.normalize(v));
+                    insert_row(
+                        &mut values, fields.collect(), (n - first) as usize,
+                        nulls);
+                }
+                let mut offsets = vidx.offsets()?;
+                offsets.drain(..first as usize);
+                Lookup::Memory { values: values, offsets: offsets }
+            };
+            return Ok(ValueIndex {
+                rdr: rdr,
+                lookup: lookup,
+                first: first,
+                num_rows: num_rows,
+                nulls: nulls,
+            });
+        }
+        let mut values = HashMap::with_capacity(10000);
+        let mut offsets = Vec::with_capacity(10000);
 
-        // This logic is kind of tricky. Basically, we want to include
-        // the header row in the line index (because that's what csv::index
-        // does), but we don't want to include header values in the ValueIndex.
+        // We don't want to include header values in the ValueIndex, so if
+        // there are headers, we make sure that we've parsed them. If there
+        // aren't, we seek to the beginning and index everything.
         if !rdr.has_headers() {
-            // ... so if there are no headers, we seek to the beginning and
-            // index everything.
             let mut pos = csv::Position::new();
             pos.set_byte(0);
             rdr.seek(pos)?;
         } else {
-            // ... and if there are headers, we make sure that we've parsed
-            // them, and write the offset of the header row to the index.
             rdr.byte_headers()?;
-            row_idx.write_u64::<BigEndian>(0)?;
-            count += 1;
         }
 
         let mut row = csv::ByteRecord::new();
         while rdr.read_byte_record(&mut row)? {
-            // This is a bit hokey. We're doing this manually instead of using
-            // the `csv-index` crate directly so that we can create both
-            // indexes in one pass.
-            row_idx.write_u64::<BigEndian>(row.position().unwrap().byte())?;
-
             let fields: Vec<_> = sel
                 .select(&row)
-                .map(|v| transform(v, casei))
+                .map(|v| norm.normalize(v))
                 .collect();
-            if nulls || !fields.iter().any(|f| f.is_empty()) {
-                match val_idx.entry(fields) {
-                    Entry::Vacant(v) => {
-                        let mut rows = Vec::with_capacity(4);
-                        rows.push(rowi);
-                        v.insert(rows);
-                    }
-                    Entry::Occupied(mut v) => {
-                        v.get_mut().push(rowi);
-                    }
-                }
-            }
-            rowi += 1;
-            count += 1;
+            insert_row(&mut values, fields, offsets.len(), nulls);
+            offsets.push(row.position().unwrap().byte());
         }
-
-        row_idx.write_u64::<BigEndian>(count as u64)?;
-        let idx = Indexed::open(rdr, io::Cursor::new(row_idx.into_inner()))?;
         Ok(ValueIndex {
-            values: val_idx,
-            idx: idx,
-            num_rows: rowi,
+            rdr: rdr,
+            num_rows: offsets.len(),
+            lookup: Lookup::Memory { values: values, offsets: offsets },
+            first: first,
+            nulls: nulls,
         })
     }
+
+    /// Return the rows that have the (normalized) key `key`.
+    fn rows(&mut self, key: &[ByteString]) -> CliResult<Vec<usize>> {
+        if !self.nulls && key.iter().any(|f| f.is_empty()) {
+            return Ok(vec![]);
+        }
+        match self.lookup {
+            Lookup::Memory { ref values, .. } => {
+                Ok(values.get(key).cloned().unwrap_or_else(Vec::new))
+            }
+            Lookup::File(ref mut vidx) => {
+                let first = self.first;
+                Ok(vidx.find_trimmed(key)?
+                       .into_iter()
+                       .filter(|&(n, _)| n >= first)
+                       .map(|(n, _)| (n - first) as usize)
+                       .collect())
+            }
+        }
+    }
+
+    /// Read row `rowi` into `record`.
+    fn read_row(
+        &mut self,
+        rowi: usize,
+        record: &mut csv::ByteRecord,
+    ) -> CliResult<()> {
+        let offset = match self.lookup {
+            Lookup::Memory { ref offsets, .. } => offsets[rowi],
+            Lookup::File(ref mut vidx) => {
+                vidx.offset(rowi as u64 + self.first)?
+            }
+        };
+        let mut pos = csv::Position::new();
+        pos.set_byte(offset);
+        self.rdr.seek(pos)?;
+        self.rdr.read_byte_record(record)?;
+        Ok(())
+    }
+}
+
+fn insert_row(
+    val_idx: &mut HashMap<Vec<ByteString>, Vec<usize>>,
+    fields: Vec<ByteString>,
+    rowi: usize,
+    nulls: bool,
+) {
+    if nulls || !fields.iter().any(|f| f.is_empty()) {
+        match val_idx.entry(fields) {
+            Entry::Vacant(v) => {
+                let mut rows = Vec::with_capacity(4);
+                rows.push(rowi);
+                v.insert(rows);
+            }
+            Entry::Occupied(mut v) => {
+                v.get_mut().push(rowi);
+            }
+        }
+    }
 }
 
 impl<R> fmt::Debug for ValueIndex<R> {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let values = match self.lookup {
+            Lookup::Memory { ref values, .. } => values,
+            Lookup::File(_) => return writeln!(f, "(value index file)"),
+        };
         // Sort the values by order of first appearance.
-        let mut kvs = self.values.iter().collect::<Vec<_>>();
+        let mut kvs = values.iter().collect::<Vec<_>>();
         kvs.sort_by(|&(_, v1), &(_, v2)| v1[0].cmp(&v2[0]));
         for (keys, rows) in kvs.into_iter() {
             // This is just for debugging, so assume Unicode for now.
@@ -411,26 +989,190 @@ impl<R> fmt::Debug for ValueIndex<R> {
     }
 }
 
-fn get_row_key(
-    sel: &Selection,
-    row: &csv::ByteRecord,
-    casei: bool,
-) -> Vec<ByteString> {
-    sel.select(row).map(|v| transform(&v, casei)).collect()
+/// RangeIndex groups the rows of the second input of an as-of or interval
+/// join by the values of their equality join columns, and sorts each group
+/// by the ordered key (or the start of the interval).
+struct RangeIndex<R> {
+    rdr: csv::Reader<R>,
+    groups: HashMap<Vec<ByteString>, Vec<RangeRow>>,
+}
+
+struct RangeRow {
+    start: RangeKey,
+    /// The end of the interval, for interval joins.
+    end: Option<RangeKey>,
+    pos: csv::Position,
 }
 
-fn transform(bs: &[u8], casei: bool) -> ByteString {
-    match str::from_utf8(bs) {
-        Err(_) => bs.to_vec(),
-        Ok(s) => {
-            if !casei {
-                s.trim().as_bytes().to_vec()
+impl<R: io::Read + io::Seek> RangeIndex<R> {
+    fn new(
+        mut rdr: csv::Reader<R>,
+        sel: &Selection,
+        nby: usize,
+        interval: bool,
+        norm: &KeyNormalizer,
+        formats: &Formats,
+        nulls: bool,
+    ) -> CliResult<RangeIndex<R>> {
+        let mut groups = HashMap::new();
+        let mut row = csv::ByteRecord::new();
+        while rdr.read_byte_record(&mut row)? {
+            let (by, start) = range_key(sel, nby, &row, norm, formats);
+            let start = match start {
+                None => continue,
+                Some(start) => start,
+            };
+            let end = if interval {
+                let end = norm.normalize(&row[sel[nby + 1]]);
+                match RangeKey::new(&end, formats) {
+                    None => continue,
+                    end => end,
+                }
             } else {
-                let norm: String =
-                    s.trim().chars()
-                     .map(|c| c.to_lowercase().next().unwrap()).collect();
-                norm.into_bytes()
+                None
+            };
+            if !nulls && by.iter().any(|f| f.is_empty()) {
+                continue;
             }
+            groups.entry(by).or_insert_with(Vec::new).push(RangeRow {
+                start: start,
+                end: end,
+                pos: row.position().unwrap().clone(),
+            });
+        }
+        // This sort is stable, so the last of the rows with the same key
+        // is still the last one in the input.
+        for rows in groups.values_mut() {
+            rows.sort_by(|r1, r2| r1.start.cmp(&r2.start));
+        }
+        Ok(RangeIndex { rdr: rdr, groups: groups })
+    }
+
+    /// Return the positions of the rows that match the key `by` and the
+    /// ordered key `at`.
+    fn find(
+        &self,
+        by: &[ByteString],
+        at: &RangeKey,
+        interval: bool,
+    ) -> Vec<csv::Position> {
+        let rows = match self.groups.get(by) {
+            None => return vec![],
+            Some(rows) => rows,
+        };
+        // Find the rows that start at or before `at`.
+        let n = match rows.binary_search_by(|r| {
+            if r.start <= *at { Ordering::Less } else { Ordering::Greater }
+        }) {
+            Ok(n) | Err(n) => n,
+        };
+        if interval {
+            rows[..n].iter()
+                     .filter(|r| r.end.as_ref().map_or(false, |end| at < end))
+                     .map(|r| r.pos.clone())
+                     .collect()
+        } else {
+            rows[..n].last().map(|r| r.pos.clone()).into_iter().collect()
         }
     }
+
+    fn read(
+        &mut self,
+        pos: csv::Position,
+        row: &mut csv::ByteRecord,
+    ) -> CliResult<()> {
+        self.rdr.seek(pos)?;
+        self.rdr.read_byte_record(row)?;
+        Ok(())
+    }
+}
+
+/// A value of the ordered join column of an as-of or interval join.
+///
+/// Numbers are compared by value, dates and date times chronologically, and
+/// anything else as text. Numbers sort before dates, which sort before text.
+#[derive(Clone, Debug)]
+enum RangeKey {
+    Number(f64),
+    Date(NaiveDateTime),
+    Text(ByteString),
+}
+
+impl RangeKey {
+    fn new(v: &[u8], formats: &Formats) -> Option<RangeKey> {
+        if v.is_empty() {
+            return None;
+        }
+        let s = match ::std::str::from_utf8(v) {
+            Err(_) => return Some(RangeKey::Text(v.to_vec())),
+            Ok(s) => s,
+        };
+        Some(match s.parse::<f64>() {
+            Ok(n) if !f64::is_nan(n) => RangeKey::Number(n),
+            _ => match formats.parse_date(s) {
+                Some((_, dt)) => RangeKey::Date(dt),
+                None => RangeKey::Text(v.to_vec()),
+            },
+        })
+    }
+}
+
+impl RangeKey {
+    /// The position of the kind of key in the order of kinds.
+    fn rank(&self) -> u8 {
+        match *self {
+            RangeKey::Number(_) => 0,
+            RangeKey::Date(_) => 1,
+            RangeKey::Text(_) => 2,
+        }
+    }
+}
+
+impl PartialEq for RangeKey {
+    fn eq(&self, other: &RangeKey) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for RangeKey {}
+
+impl PartialOrd for RangeKey {
+    fn partial_cmp(&self, other: &RangeKey) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for RangeKey {
+    fn cmp(&self, other: &RangeKey) -> Ordering {
+        match (self, other) {
+            (&RangeKey::Number(n1), &RangeKey::Number(n2)) => {
+                n1.partial_cmp(&n2).unwrap()
+            }
+            (&RangeKey::Date(ref d1), &RangeKey::Date(ref d2)) => d1.cmp(d2),
+            (&RangeKey::Text(ref s1), &RangeKey::Text(ref s2)) => s1.cmp(s2),
+            (k1, k2) => k1.rank().cmp(&k2.rank()),
+        }
+    }
+}
+
+/// Split the join key of a row into the values of its first `nby` join
+/// columns, which are compared by equality, and the ordered value of the
+/// join column after them.
+fn range_key(
+    sel: &Selection,
+    nby: usize,
+    row: &csv::ByteRecord,
+    norm: &KeyNormalizer,
+    formats: &Formats,
+) -> (Vec<ByteString>, Option<RangeKey>) {
+    let by = sel[..nby].iter().map(|&i| norm.normalize(&row[i])).collect();
+    (by, RangeKey::new(&norm.normalize(&row[sel[nby]]), formats))
+}
+
+fn get_row_key(
+    sel: &Selection,
+    row: &csv::ByteRecord,
+    norm: &KeyNormalizer,
+) -> Vec<ByteString> {
+    sel.select(row).map(|v| norm.normalize(v)).collect()
 }
@@ -1,9 +1,11 @@
 pub mod cat;
 pub mod count;
+pub mod dedup;
 pub mod fixlengths;
 pub mod flatten;
 pub mod fmt;
 pub mod frequency;
+pub mod groupby;
 pub mod headers;
 pub mod index;
 pub mod input;
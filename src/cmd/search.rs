@@ -1,8 +1,12 @@
+use std::fs;
+use std::io;
+
 use csv;
-use regex::bytes::RegexBuilder;
+use regex::bytes::{Regex, RegexBuilder};
 
 use CliResult;
 use config::{Config, Delimiter};
+use index::ValueIndexReader;
 use select::SelectColumns;
 use util;
 
@@ -14,6 +18,10 @@ then the row is written to the output. The columns to search can be limited
 with the '--select' flag (but the full row is still written to the output if
 there is a match).
 
+When a value index of the searched columns was created with
+'xsv index --columns', the regex is applied to each distinct key in the index
+instead, and only the rows that are written are parsed.
+
 Usage:
     xsv search [options] <regex> [<input>]
     xsv search --help
@@ -66,6 +74,20 @@ pub fn run(argv: &[&str]) -> CliResult<()> {
     if !rconfig.no_headers {
         wtr.write_record(&headers)?;
     }
+    if let Some(vidx) = rconfig.value_index_file(&sel)? {
+        let mut rdr = rconfig.reader_file()?;
+        let offsets = search_value_index(
+            !rconfig.no_headers, vidx, &pattern, args.flag_invert_match)?;
+        let mut record = csv::ByteRecord::new();
+        for offset in offsets {
+            let mut pos = csv::Position::new();
+            pos.set_byte(offset);
+            rdr.seek(pos)?;
+            rdr.read_byte_record(&mut record)?;
+            wtr.write_byte_record(&record)?;
+        }
+        return Ok(wtr.flush()?);
+    }
     let mut record = csv::ByteRecord::new();
     while rdr.read_byte_record(&mut record)? {
         let mut m = sel.select(&record).any(|f| pattern.is_match(f));
@@ -78,3 +100,37 @@ pub fn run(argv: &[&str]) -> CliResult<()> {
     }
     Ok(wtr.flush()?)
 }
+
+/// Find the byte offsets of the rows whose key in a value index matches
+/// `pattern` (or doesn't, when `invert` is set), in the order of the data.
+fn search_value_index(
+    has_headers: bool,
+    vidx: fs::File,
+    pattern: &Regex,
+    invert: bool,
+) -> CliResult<Vec<u64>> {
+    let mut vidx = ValueIndexReader::new(io::BufReader::new(vidx))?;
+    // The header row is indexed too, but it is never searched.
+    let first = if has_headers { 1 } else { 0 };
+    let mut rows = vec![];
+    let mut last: Option<(Vec<util::ByteString>, bool)> = None;
+    for (n, key) in vidx.entries()? {
+        if n < first {
+            continue;
+        }
+        // Equal keys are mostly next to each other, so only run the regex
+        // when the key changes.
+        let m = match last {
+            Some((ref k, m)) if *k == key => m,
+            _ => key.iter().any(|f| pattern.is_match(f)),
+        };
+        if m != invert {
+            rows.push(n);
+        }
+        last = Some((key, m));
+    }
+    rows.sort();
+
+    let offsets = vidx.offsets()?;
+    Ok(rows.into_iter().map(|n| offsets[n as usize]).collect())
+}
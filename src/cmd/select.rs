@@ -30,9 +30,25 @@ more indexing). Finally, column ranges can be specified.
   Re-order and duplicate columns arbitrarily:
   $ xsv select 3-1,Header3-Header1,Header1,Foo[2],Header1
 
+  Select the columns whose names match a regex, or a glob where '*' matches
+  any text:
+  $ xsv select '/^price_/'
+  $ xsv select '*_id'
+
+  Select the columns of a type, as inferred by 'xsv stats' from the first
+  1000 rows: ':numeric' (integers and floats), ':integer', ':float', ':date'
+  (dates and date times), ':boolean' or ':string'. This reads the data twice,
+  so it can't be used with <stdin>:
+  $ xsv select ':numeric'
+  $ xsv select '!:string'
+
   Quote column names that conflict with selector syntax:
   $ xsv select '\"Date - Opening\",\"Date - Actual Closing\"'
 
+  This includes names that contain '*' or start with '/', which would
+  otherwise be read as a glob or a regex:
+  $ xsv select '\"Price*Qty\",\"/path\"'
+
 Usage:
     xsv select [options] [--] <selection> [<input>]
     xsv select --help
@@ -1,9 +1,12 @@
 use std::fs;
+use std::io;
 
+use csv;
 
 use CliResult;
 use config::{Config, Delimiter};
-use index::Indexed;
+use index::{Indexed, ValueIndexReader};
+use select::SelectColumns;
 use util;
 
 static USAGE: &'static str = "
@@ -21,6 +24,11 @@ first. Namely, a slice on an index requires parsing just the rows that are
 sliced. Without an index, all rows up to the first row in the slice must be
 parsed.
 
+With --key, only the rows that have the given values in the --key-columns are
+sliced, and the range is taken from those rows. This is much faster when a
+value index of those columns was created with 'xsv index --columns', since
+only the matching rows are then parsed.
+
 Usage:
     xsv slice [options] [<input>]
 
@@ -30,6 +38,13 @@ slice options:
     -l, --len <arg>        The length of the slice (can be used instead
                            of --end).
     -i, --index <arg>      Slice a single record (shortcut for -s N -l 1).
+    -k, --key <values>     Only slice the rows whose key is <values>. Keys
+                           with more than one column are given as a single
+                           CSV record (using the --delimiter of the input),
+                           so values that contain the delimiter must be
+                           quoted, e.g., '\"Boston, MA\",02108'.
+    -K, --key-columns <arg>  The columns that make up the key for --key.
+                           See 'xsv select --help' for the format details.
 
 Common options:
     -h, --help             Display this message
@@ -48,6 +63,8 @@ struct Args {
     flag_end: Option<usize>,
     flag_len: Option<usize>,
     flag_index: Option<usize>,
+    flag_key: Option<String>,
+    flag_key_columns: Option<SelectColumns>,
     flag_output: Option<String>,
     flag_no_headers: bool,
     flag_delimiter: Option<Delimiter>,
@@ -55,6 +72,9 @@ struct Args {
 
 pub fn run(argv: &[&str]) -> CliResult<()> {
     let args: Args = util::get_args(USAGE, argv)?;
+    if let Some(ref key) = args.flag_key {
+        return args.by_key(key);
+    }
     match args.rconfig().indexed()? {
         None => args.no_index(),
         Some(idxed) => args.with_index(idxed),
@@ -93,6 +113,51 @@ impl Args {
         Ok(())
     }
 
+    fn by_key(&self, key: &str) -> CliResult<()> {
+        let columns = match self.flag_key_columns {
+            None => return fail!("--key requires --key-columns."),
+            Some(ref columns) => columns.clone(),
+        };
+        let rconfig = self.rconfig().select(columns);
+        let mut rdr = rconfig.reader()?;
+        let sel = rconfig.selection(rdr.byte_headers()?)?;
+        let key = parse_key(key, rconfig.delimiter)?;
+        let key: Vec<&[u8]> = key.iter().collect();
+        if key.len() != sel.len() {
+            return fail!(format!(
+                "--key has {} values, but --key-columns selects {} columns.",
+                key.len(), sel.len()));
+        }
+
+        let mut wtr = self.wconfig().writer()?;
+        rconfig.write_headers(&mut rdr, &mut wtr)?;
+        let (start, end) = self.range()?;
+        match rconfig.value_index_file(&sel)? {
+            None => {
+                let rows = rdr.byte_records().filter(|r| match *r {
+                    Ok(ref r) => sel.select(r).eq(key.iter().cloned()),
+                    Err(_) => true,
+                });
+                for r in rows.skip(start).take(end - start) {
+                    wtr.write_byte_record(&r?)?;
+                }
+            }
+            Some(vidx) => {
+                let mut rdr = rconfig.reader_file()?;
+                let offsets = key_offsets(!rconfig.no_headers, vidx, &key)?;
+                let mut row = csv::ByteRecord::new();
+                for &offset in offsets.iter().skip(start).take(end - start) {
+                    let mut pos = csv::Position::new();
+                    pos.set_byte(offset);
+                    rdr.seek(pos)?;
+                    rdr.read_byte_record(&mut row)?;
+                    wtr.write_byte_record(&row)?;
+                }
+            }
+        }
+        Ok(wtr.flush()?)
+    }
+
     fn range(&self) -> Result<(usize, usize), String> {
         util::range(
             self.flag_start, self.flag_end, self.flag_len, self.flag_index)
@@ -108,3 +173,38 @@ impl Args {
         Config::new(&self.flag_output)
     }
 }
+
+/// Parse the value of --key, which is a single CSV record that uses the
+/// same delimiter as the input.
+fn parse_key(key: &str, delimiter: u8) -> CliResult<csv::ByteRecord> {
+    let mut rdr = csv::ReaderBuilder::new()
+        .has_headers(false)
+        .delimiter(delimiter)
+        .from_reader(key.as_bytes());
+    let mut record = csv::ByteRecord::new();
+    if !rdr.read_byte_record(&mut record)? {
+        // An empty key is a single empty value.
+        record.push_field(b"");
+    }
+    Ok(record)
+}
+
+/// Find the byte offsets of the rows with the given key in a value index.
+fn key_offsets(
+    has_headers: bool,
+    vidx: fs::File,
+    key: &[&[u8]],
+) -> CliResult<Vec<u64>> {
+    let mut vidx = ValueIndexReader::new(io::BufReader::new(vidx))?;
+    // The index orders keys with whitespace trimmed, so the rows it finds
+    // still have to match exactly. The header row is indexed too, but it
+    // isn't a match.
+    let first = if has_headers { 1 } else { 0 };
+    let mut offsets = vec![];
+    for (n, k) in vidx.find_trimmed(key)? {
+        if n >= first && k.iter().map(|v| &**v).eq(key.iter().cloned()) {
+            offsets.push(vidx.offset(n)?);
+        }
+    }
+    Ok(offsets)
+}
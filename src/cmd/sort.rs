@@ -1,9 +1,23 @@
 use std::cmp;
+use std::collections::BinaryHeap;
+use std::env;
+use std::fs;
+use std::io;
+use std::iter;
+use std::path::{Path, PathBuf};
+use std::process;
 
-use CliResult;
+use channel;
+use csv;
+use serde::de::{Deserializer, Deserialize, Error};
+use threadpool::ThreadPool;
+
+use {CliError, CliResult};
 use config::{Config, Delimiter};
-use select::SelectColumns;
-use util;
+use index::Indexed;
+use normalize::KeyNormalizer;
+use select::{SelectColumns, Selection};
+use util::{self, ByteString};
 use std::str::from_utf8;
 
 use self::Number::{Float, Int};
@@ -11,7 +25,17 @@ use self::Number::{Float, Int};
 static USAGE: &'static str = "
 Sorts CSV data lexicographically.
 
-Note that this requires reading all of the CSV data into memory.
+The sort is stable: records that compare equal are written in the same order
+as they appear in the input.
+
+Note that by default this requires reading all of the CSV data into memory.
+When --buffer-size is given, the data is instead sorted in runs of bounded
+size that are written to temporary files and merged afterwards, at most 64 at
+a time. The output is the same either way.
+
+Sorting a large file can be made much faster if you create an index for it
+first with 'xsv index'. Each job then only reads its own part of the data.
+Without an index, the data is sorted by a single job unless --jobs is given.
 
 Usage:
     xsv sort [options] [<input>]
@@ -21,6 +45,49 @@ sort options:
                            See 'xsv select --help' for the format details.
     -N, --numeric          Compare according to string numerical value
     -R, --reverse          Reverse order
+    --order <rules>        Set the order and type of each column selected
+                           with --select. This is a comma separated list with
+                           one rule per selected column, or a single rule for
+                           all of them. A rule is made of words separated by
+                           ':', e.g., 'asc,desc:num,nocase:nulls-last'.
+                           The words are 'asc' or 'desc' for the direction,
+                           'text', 'num', 'natural' (so that 'a2' comes
+                           before 'a10') or 'nocase' for the type, and
+                           'nulls-first' or 'nulls-last' to put empty values
+                           first or last regardless of direction. Anything
+                           not set in a rule is taken from -N and -R.
+    --stable               Keep records that compare equal in input order.
+                           Sorting is always stable, so this is accepted
+                           for compatibility with sort(1).
+    -u, --unique           Only write the first record (in input order) of
+                           each set of records that compare equal on the
+                           sort key.
+    --normalize <list>     A comma separated list of normalizers applied to
+                           the selected values before they are compared,
+                           e.g., 'nfc,lower'. With --unique, this removes
+                           records whose keys only differ before they are
+                           normalized. See 'xsv join --help' for the list.
+    --key-regex <regex>    Only compare the part of each selected value
+                           matched by <regex> (or by its first capture
+                           group).
+    -B, --buffer-size <arg>  Sort with bounded memory. At most <arg> bytes of
+                           CSV data are sorted in memory at once. Each sorted
+                           run is written to a temporary file and the runs
+                           are merged at the end. The suffixes K, M and G
+                           are accepted (e.g., 512M).
+    --tmpdir <dir>         The directory in which to write temporary files
+                           when --buffer-size is set. Defaults to the
+                           system's temporary directory.
+    -j, --jobs <arg>       The number of jobs to run in parallel. The data
+                           is sorted in chunks which are then merged.
+                           This works better when the given CSV data has
+                           an index already created. Note that a file handle
+                           is opened for each job. More than one job cannot
+                           be used with a buffer size.
+                           When set to '0', the number of jobs is set to the
+                           number of CPUs detected if there is an index, and
+                           to 1 otherwise.
+                           [default: 0]
 
 Common options:
     -h, --help             Display this message
@@ -33,12 +100,19 @@ Common options:
                            Must be a single character. (default: ,)
 ";
 
-#[derive(Deserialize)]
+#[derive(Clone, Deserialize)]
 struct Args {
     arg_input: Option<String>,
     flag_select: SelectColumns,
     flag_numeric: bool,
     flag_reverse: bool,
+    flag_order: Option<String>,
+    flag_unique: bool,
+    flag_normalize: Option<String>,
+    flag_key_regex: Option<String>,
+    flag_buffer_size: Option<ByteSize>,
+    flag_tmpdir: Option<String>,
+    flag_jobs: usize,
     flag_output: Option<String>,
     flag_no_headers: bool,
     flag_delimiter: Option<Delimiter>,
@@ -46,54 +120,596 @@ struct Args {
 
 pub fn run(argv: &[&str]) -> CliResult<()> {
     let args: Args = util::get_args(USAGE, argv)?;
-    let numeric = args.flag_numeric;
-    let reverse = args.flag_reverse;
-    let rconfig = Config::new(&args.arg_input)
-        .delimiter(args.flag_delimiter)
-        .no_headers(args.flag_no_headers)
-        .select(args.flag_select);
-
+    if args.flag_buffer_size.is_some() && args.flag_jobs > 1 {
+        return fail!("--jobs cannot be used with --buffer-size.");
+    }
+    let rconfig = args.rconfig();
     let mut rdr = rconfig.reader()?;
 
     let headers = rdr.byte_headers()?.clone();
-    let sel = rconfig.selection(&headers)?;
-
-    let mut all = rdr.byte_records().collect::<Result<Vec<_>, _>>()?;
-    match (numeric, reverse) {
-        (false, false) =>
-            all.sort_by(|r1, r2| {
-                let a = sel.select(r1);
-                let b = sel.select(r2);
-                iter_cmp(a, b)
-            }),
-        (true, false) =>
-            all.sort_by(|r1, r2| {
-                let a = sel.select(r1);
-                let b = sel.select(r2);
-                iter_cmp_num(a, b)
-            }),
-        (false, true) =>
-            all.sort_by(|r1, r2| {
-                let a = sel.select(r1);
-                let b = sel.select(r2);
-                iter_cmp(b, a)
-            }),
-        (true, true) =>
-            all.sort_by(|r1, r2| {
-                let a = sel.select(r1);
-                let b = sel.select(r2);
-                iter_cmp_num(b, a)
-            }),
-    }
+    let cmp = args.comparator(rconfig.selection(&headers)?)?;
 
     let mut wtr = Config::new(&args.flag_output).writer()?;
     rconfig.write_headers(&mut rdr, &mut wtr)?;
-    for r in all.into_iter() {
-        wtr.write_byte_record(&r)?;
+    {
+        let mut last: Option<csv::ByteRecord> = None;
+        let write = |r: csv::ByteRecord| -> CliResult<()> {
+            if args.flag_unique {
+                if let Some(ref prev) = last {
+                    if cmp.cmp(prev, &r) == cmp::Ordering::Equal {
+                        return Ok(());
+                    }
+                }
+                wtr.write_byte_record(&r)?;
+                last = Some(r);
+            } else {
+                wtr.write_byte_record(&r)?;
+            }
+            Ok(())
+        };
+        match args.flag_buffer_size {
+            None => {
+                let chunks = match rconfig.indexed()? {
+                    Some(idx) => args.parallel_sort_indexed(idx, &cmp)?,
+                    None => {
+                        let all = rdr.byte_records()
+                                     .collect::<Result<Vec<_>, _>>()?;
+                        let njobs = cmp::max(args.flag_jobs, 1);
+                        args.parallel_sort(all, &cmp, njobs)?
+                    }
+                };
+                let sources = chunks
+                    .into_iter()
+                    .map(|chunk| chunk.into_iter().map(Ok::<_, CliError>))
+                    .collect();
+                merge(sources, &cmp, write)?;
+            }
+            Some(ByteSize(limit)) => {
+                let runs = args.sorted_runs(&mut rdr, &cmp, limit)?;
+                let runs = args.merge_runs(runs, &cmp)?;
+                let sources = runs
+                    .iter()
+                    .map(|run| run.records())
+                    .collect::<Result<Vec<_>, _>>()?;
+                merge(sources, &cmp, write)?;
+            }
+        }
     }
     Ok(wtr.flush()?)
 }
 
+impl Args {
+    fn rconfig(&self) -> Config {
+        Config::new(&self.arg_input)
+            .delimiter(self.flag_delimiter)
+            .no_headers(self.flag_no_headers)
+            .select(self.flag_select.clone())
+    }
+
+    fn njobs(&self) -> usize {
+        if self.flag_jobs == 0 { util::num_cpus() } else { self.flag_jobs }
+    }
+
+    /// Sort `all` by splitting it into one chunk for each of `njobs` jobs
+    /// and sorting the chunks in parallel. The sorted chunks are returned in
+    /// input order.
+    fn parallel_sort(
+        &self,
+        mut all: Vec<csv::ByteRecord>,
+        cmp: &Comparator,
+        njobs: usize,
+    ) -> CliResult<Vec<Vec<csv::ByteRecord>>> {
+        if njobs == 1 || all.len() < 2 {
+            all.sort_by(|r1, r2| cmp.cmp(r1, r2));
+            return Ok(vec![all]);
+        }
+        let chunk_size = util::chunk_size(all.len(), njobs);
+        let nchunks = util::num_of_chunks(all.len(), chunk_size);
+
+        let pool = ThreadPool::new(njobs);
+        let (send, recv) = channel::bounded(0);
+        let mut records = all.into_iter();
+        for i in 0..nchunks {
+            let (send, cmp) = (send.clone(), cmp.clone());
+            let mut chunk: Vec<_> =
+                records.by_ref().take(chunk_size).collect();
+            pool.execute(move || {
+                chunk.sort_by(|r1, r2| cmp.cmp(r1, r2));
+                send.send((i, Ok(chunk)));
+            });
+        }
+        drop(send);
+        collect_chunks(nchunks, recv)
+    }
+
+    /// Like `parallel_sort`, but each job reads its own chunk of the data
+    /// by seeking through the index.
+    fn parallel_sort_indexed(
+        &self,
+        idx: Indexed<fs::File, fs::File>,
+        cmp: &Comparator,
+    ) -> CliResult<Vec<Vec<csv::ByteRecord>>> {
+        if self.njobs() == 1 || idx.count() == 0 {
+            let mut rdr = self.rconfig().reader()?;
+            let all = rdr.byte_records().collect::<Result<Vec<_>, _>>()?;
+            return self.parallel_sort(all, cmp, 1);
+        }
+        let chunk_size = util::chunk_size(idx.count() as usize, self.njobs());
+        let nchunks = util::num_of_chunks(idx.count() as usize, chunk_size);
+
+        let pool = ThreadPool::new(self.njobs());
+        let (send, recv) = channel::bounded(0);
+        for i in 0..nchunks {
+            let (send, args, cmp) = (send.clone(), self.clone(), cmp.clone());
+            pool.execute(move || {
+                let chunk = args.read_chunk(i * chunk_size, chunk_size)
+                    .map(|mut chunk| {
+                        chunk.sort_by(|r1, r2| cmp.cmp(r1, r2));
+                        chunk
+                    });
+                send.send((i, chunk));
+            });
+        }
+        drop(send);
+        collect_chunks(nchunks, recv)
+    }
+
+    fn read_chunk(
+        &self,
+        start: usize,
+        len: usize,
+    ) -> CliResult<Vec<csv::ByteRecord>> {
+        let mut idx = match self.rconfig().indexed()? {
+            None => return fail!("The index disappeared while sorting."),
+            Some(idx) => idx,
+        };
+        idx.seek(start as u64)?;
+        Ok(idx.byte_records().take(len).collect::<Result<Vec<_>, _>>()?)
+    }
+
+    fn comparator(&self, sel: Selection) -> CliResult<Comparator> {
+        let kind =
+            if self.flag_numeric { KeyKind::Numeric } else { KeyKind::Text };
+        let default = SortKey {
+            reverse: self.flag_reverse,
+            kind: kind,
+            nulls: None,
+        };
+        let rules = match self.flag_order {
+            None => {
+                return Ok(Comparator {
+                    sel: sel,
+                    keys: None,
+                    default: default,
+                    norm: self.normalizer()?,
+                });
+            }
+            Some(ref order) => {
+                order.split(',')
+                     .map(|rule| default.with_rule(rule))
+                     .collect::<Result<Vec<_>, _>>()?
+            }
+        };
+        let keys = match rules.len() {
+            1 => vec![rules[0]; sel.len()],
+            n if n == sel.len() => rules,
+            n => return fail!(format!(
+                "--order has {} rules, but {} columns were selected.",
+                n, sel.len())),
+        };
+        Ok(Comparator {
+            sel: sel,
+            keys: Some(keys),
+            default: default,
+            norm: self.normalizer()?,
+        })
+    }
+
+    fn normalizer(&self) -> CliResult<KeyNormalizer> {
+        Ok(KeyNormalizer::new()
+            .steps(&self.flag_normalize)?
+            .regex(&self.flag_key_regex)?)
+    }
+
+    /// Sort the records of `rdr` in runs holding at most `limit` bytes of
+    /// CSV data each, and spill every run to a temporary file.
+    fn sorted_runs<R: io::Read>(
+        &self,
+        rdr: &mut csv::Reader<R>,
+        cmp: &Comparator,
+        limit: usize,
+    ) -> CliResult<Vec<TempRun>> {
+        let tmpdir = self.tmpdir();
+        let mut runs = vec![];
+        let mut buf = vec![];
+        let mut size = 0;
+        for r in rdr.byte_records() {
+            let r = r?;
+            size += r.as_slice().len();
+            buf.push(r);
+            if size >= limit {
+                buf.sort_by(|r1, r2| cmp.cmp(r1, r2));
+                runs.push(TempRun::create(&tmpdir, runs.len(), &buf)?);
+                buf.clear();
+                size = 0;
+            }
+        }
+        if !buf.is_empty() {
+            buf.sort_by(|r1, r2| cmp.cmp(r1, r2));
+            runs.push(TempRun::create(&tmpdir, runs.len(), &buf)?);
+        }
+        Ok(runs)
+    }
+
+    /// Merge consecutive groups of runs into bigger runs until there are at
+    /// most `MAX_MERGE_RUNS` of them, so that the final merge doesn't open
+    /// too many files at once.
+    fn merge_runs(
+        &self,
+        mut runs: Vec<TempRun>,
+        cmp: &Comparator,
+    ) -> CliResult<Vec<TempRun>> {
+        let tmpdir = self.tmpdir();
+        let mut n = runs.len();
+        while runs.len() > MAX_MERGE_RUNS {
+            let mut merged = vec![];
+            let mut rest = runs.into_iter();
+            loop {
+                let group: Vec<TempRun> =
+                    rest.by_ref().take(MAX_MERGE_RUNS).collect();
+                if group.len() <= 1 {
+                    merged.extend(group);
+                    break;
+                }
+                let sources = group
+                    .iter()
+                    .map(|run| run.records())
+                    .collect::<Result<Vec<_>, _>>()?;
+                let (run, mut wtr) = TempRun::open(&tmpdir, n)?;
+                n += 1;
+                merge(sources, cmp, |r| Ok(wtr.write_byte_record(&r)?))?;
+                wtr.flush()?;
+                merged.push(run);
+            }
+            runs = merged;
+        }
+        Ok(runs)
+    }
+
+    fn tmpdir(&self) -> PathBuf {
+        match self.flag_tmpdir {
+            None => env::temp_dir(),
+            Some(ref dir) => PathBuf::from(dir),
+        }
+    }
+}
+
+/// Comparator orders records on the selected columns.
+///
+/// Both the in-memory and the external sort use the same comparator, which
+/// is what makes their output identical.
+#[derive(Clone)]
+struct Comparator {
+    sel: Selection,
+    /// The key of each selected column, with --order. Without it, the
+    /// selected columns are compared together with -N and -R.
+    keys: Option<Vec<SortKey>>,
+    default: SortKey,
+    /// Applied to the selected values before comparing them.
+    norm: KeyNormalizer,
+}
+
+impl Comparator {
+    fn cmp(
+        &self,
+        r1: &csv::ByteRecord,
+        r2: &csv::ByteRecord,
+    ) -> cmp::Ordering {
+        if self.norm.is_identity() {
+            return self.cmp_keys(self.sel.select(r1), self.sel.select(r2));
+        }
+        let key1: Vec<ByteString> =
+            self.sel.select(r1).map(|v| self.norm.normalize(v)).collect();
+        let key2: Vec<ByteString> =
+            self.sel.select(r2).map(|v| self.norm.normalize(v)).collect();
+        self.cmp_keys(key1.iter().map(|v| &**v), key2.iter().map(|v| &**v))
+    }
+
+    fn cmp_keys<'a, L, R>(&self, a: L, b: R) -> cmp::Ordering
+            where L: Iterator<Item=&'a [u8]>, R: Iterator<Item=&'a [u8]> {
+        let keys = match self.keys {
+            Some(ref keys) => keys,
+            None => {
+                return match (self.default.kind, self.default.reverse) {
+                    (KeyKind::Numeric, false) => iter_cmp_num(a, b),
+                    (KeyKind::Numeric, true) => iter_cmp_num(b, a),
+                    (_, false) => iter_cmp(a, b),
+                    (_, true) => iter_cmp(b, a),
+                };
+            }
+        };
+        for (key, (a, b)) in keys.iter().zip(a.zip(b)) {
+            match key.cmp(a, b) {
+                cmp::Ordering::Equal => (),
+                non_eq => return non_eq,
+            }
+        }
+        cmp::Ordering::Equal
+    }
+}
+
+/// SortKey describes how to order the values of a single column.
+#[derive(Clone, Copy, Debug)]
+struct SortKey {
+    reverse: bool,
+    kind: KeyKind,
+    /// When set, empty values are placed first or last regardless of
+    /// `reverse`. Otherwise they are ordered like any other value.
+    nulls: Option<Nulls>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum KeyKind {
+    Text,
+    Numeric,
+    Natural,
+    NoCase,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Nulls {
+    First,
+    Last,
+}
+
+impl SortKey {
+    /// Return a copy of this key with the words in `rule` applied to it.
+    fn with_rule(&self, rule: &str) -> Result<SortKey, String> {
+        let mut key = *self;
+        for word in rule.split(':').map(|w| w.trim()) {
+            match word {
+                "" => {}
+                "asc" => key.reverse = false,
+                "desc" => key.reverse = true,
+                "text" => key.kind = KeyKind::Text,
+                "num" => key.kind = KeyKind::Numeric,
+                "natural" => key.kind = KeyKind::Natural,
+                "nocase" => key.kind = KeyKind::NoCase,
+                "nulls-first" => key.nulls = Some(Nulls::First),
+                "nulls-last" => key.nulls = Some(Nulls::Last),
+                _ => return Err(format!(
+                    "Unrecognized word '{}' in sort rule '{}'.", word, rule)),
+            }
+        }
+        Ok(key)
+    }
+
+    fn cmp(&self, a: &[u8], b: &[u8]) -> cmp::Ordering {
+        if let Some(nulls) = self.nulls {
+            let first = if nulls == Nulls::First {
+                cmp::Ordering::Less
+            } else {
+                cmp::Ordering::Greater
+            };
+            match (a.is_empty(), b.is_empty()) {
+                (true, true) => return cmp::Ordering::Equal,
+                (true, false) => return first,
+                (false, true) => return first.reverse(),
+                (false, false) => {}
+            }
+        }
+        let ord = match self.kind {
+            KeyKind::Text => a.cmp(b),
+            KeyKind::Numeric => {
+                iter_cmp_num(iter::once(a), iter::once(b))
+            }
+            KeyKind::Natural => cmp_natural(a, b),
+            KeyKind::NoCase => cmp_nocase(a, b),
+        };
+        if self.reverse { ord.reverse() } else { ord }
+    }
+}
+
+/// Gather the chunks sent by the jobs of a parallel sort, putting them back
+/// in input order.
+fn collect_chunks(
+    nchunks: usize,
+    recv: channel::Receiver<(usize, CliResult<Vec<csv::ByteRecord>>)>,
+) -> CliResult<Vec<Vec<csv::ByteRecord>>> {
+    let mut chunks: Vec<_> = (0..nchunks).map(|_| vec![]).collect();
+    for (i, chunk) in recv {
+        chunks[i] = chunk?;
+    }
+    Ok(chunks)
+}
+
+/// Merge sorted sequences of records into one sorted sequence, handing each
+/// record to `out` in order.
+///
+/// Ties are broken by the position of the source in `sources`, so merging
+/// runs of a stable sort is stable too.
+fn merge<I, F>(sources: Vec<I>, cmp: &Comparator, mut out: F) -> CliResult<()>
+        where I: Iterator<Item=CliResult<csv::ByteRecord>>,
+              F: FnMut(csv::ByteRecord) -> CliResult<()> {
+    let mut sources = sources;
+    let mut heap = BinaryHeap::with_capacity(sources.len());
+    for (i, src) in sources.iter_mut().enumerate() {
+        if let Some(rec) = src.next() {
+            heap.push(MergeEntry { rec: rec?, src: i, cmp: cmp });
+        }
+    }
+    while let Some(MergeEntry { rec, src, .. }) = heap.pop() {
+        if let Some(next) = sources[src].next() {
+            heap.push(MergeEntry { rec: next?, src: src, cmp: cmp });
+        }
+        out(rec)?;
+    }
+    Ok(())
+}
+
+struct MergeEntry<'a> {
+    rec: csv::ByteRecord,
+    src: usize,
+    cmp: &'a Comparator,
+}
+
+impl<'a> Ord for MergeEntry<'a> {
+    fn cmp(&self, other: &MergeEntry<'a>) -> cmp::Ordering {
+        // `BinaryHeap` is a max-heap, so reverse the order to pop the
+        // smallest record first.
+        self.cmp.cmp(&other.rec, &self.rec)
+            .then_with(|| other.src.cmp(&self.src))
+    }
+}
+
+impl<'a> PartialOrd for MergeEntry<'a> {
+    fn partial_cmp(&self, other: &MergeEntry<'a>) -> Option<cmp::Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl<'a> PartialEq for MergeEntry<'a> {
+    fn eq(&self, other: &MergeEntry<'a>) -> bool {
+        self.cmp(other) == cmp::Ordering::Equal
+    }
+}
+
+impl<'a> Eq for MergeEntry<'a> {}
+
+/// The most runs that are merged at once with --buffer-size. Each of them
+/// holds a file open while merging.
+const MAX_MERGE_RUNS: usize = 64;
+
+/// TempRun is a sorted run of records spilled to a temporary file.
+///
+/// The file is removed when the run is dropped.
+struct TempRun {
+    path: PathBuf,
+}
+
+impl TempRun {
+    fn create(
+        dir: &Path,
+        n: usize,
+        records: &[csv::ByteRecord],
+    ) -> CliResult<TempRun> {
+        let (run, mut wtr) = TempRun::open(dir, n)?;
+        for r in records {
+            wtr.write_byte_record(r)?;
+        }
+        wtr.flush()?;
+        Ok(run)
+    }
+
+    /// Create an empty run, and return it with a writer for its records.
+    fn open(
+        dir: &Path,
+        n: usize,
+    ) -> CliResult<(TempRun, csv::Writer<io::BufWriter<fs::File>>)> {
+        let name = format!("xsv-sort-{}-{}.csv", process::id(), n);
+        let run = TempRun { path: dir.join(name) };
+        let file = fs::OpenOptions::new()
+            .write(true)
+            .create_new(true)
+            .open(&run.path)?;
+        let wtr = csv::WriterBuilder::new()
+            .flexible(true)
+            .from_writer(io::BufWriter::new(file));
+        Ok((run, wtr))
+    }
+
+    fn records(
+        &self,
+    ) -> CliResult<Box<Iterator<Item=CliResult<csv::ByteRecord>>>> {
+        let rdr = csv::ReaderBuilder::new()
+            .flexible(true)
+            .has_headers(false)
+            .from_path(&self.path)?;
+        Ok(Box::new(rdr.into_byte_records().map(|r| Ok(r?))))
+    }
+}
+
+impl Drop for TempRun {
+    fn drop(&mut self) {
+        let _ = fs::remove_file(&self.path);
+    }
+}
+
+/// ByteSize is a number of bytes given on the command line, optionally
+/// followed by a K, M or G suffix.
+#[derive(Clone, Copy, Debug)]
+struct ByteSize(usize);
+
+impl<'de> Deserialize<'de> for ByteSize {
+    fn deserialize<D: Deserializer<'de>>(d: D) -> Result<ByteSize, D::Error> {
+        let raw = String::deserialize(d)?;
+        let (digits, scale) = match raw.chars().last() {
+            Some('K') | Some('k') => (&raw[..raw.len() - 1], 1 << 10),
+            Some('M') | Some('m') => (&raw[..raw.len() - 1], 1 << 20),
+            Some('G') | Some('g') => (&raw[..raw.len() - 1], 1 << 30),
+            _ => (&raw[..], 1),
+        };
+        let size = digits.parse::<usize>().ok()
+                         .and_then(|n| n.checked_mul(scale));
+        match size {
+            Some(n) if n > 0 => Ok(ByteSize(n)),
+            _ => Err(D::Error::custom(format!(
+                "Could not convert '{}' to a buffer size.", raw))),
+        }
+    }
+}
+
+/// Order `a` and `b` so that runs of digits are compared by their numeric
+/// value, e.g., `a2` comes before `a10`.
+fn cmp_natural(mut a: &[u8], mut b: &[u8]) -> cmp::Ordering {
+    loop {
+        match (a.first(), b.first()) {
+            (None, None) => return cmp::Ordering::Equal,
+            (None, _   ) => return cmp::Ordering::Less,
+            (_   , None) => return cmp::Ordering::Greater,
+            (Some(x), Some(y)) if x.is_ascii_digit() && y.is_ascii_digit() => {
+                let (n1, rest1) = split_digits(a);
+                let (n2, rest2) = split_digits(b);
+                let ord = n1.len().cmp(&n2.len()).then_with(|| n1.cmp(n2));
+                if ord != cmp::Ordering::Equal {
+                    return ord;
+                }
+                a = rest1;
+                b = rest2;
+            }
+            (Some(x), Some(y)) => {
+                if x != y {
+                    return x.cmp(y);
+                }
+                a = &a[1..];
+                b = &b[1..];
+            }
+        }
+    }
+}
+
+/// Split the leading run of digits off `bytes`, dropping leading zeros.
+fn split_digits(bytes: &[u8]) -> (&[u8], &[u8]) {
+    let end = bytes.iter()
+                   .position(|b| !b.is_ascii_digit())
+                   .unwrap_or(bytes.len());
+    let start = bytes[..end].iter().position(|&b| b != b'0').unwrap_or(end);
+    (&bytes[start..end], &bytes[end..])
+}
+
+/// Order `a` and `b` ignoring case. Values that aren't valid UTF-8 are
+/// compared byte-wise.
+fn cmp_nocase(a: &[u8], b: &[u8]) -> cmp::Ordering {
+    match (from_utf8(a), from_utf8(b)) {
+        (Ok(a), Ok(b)) => {
+            let a = a.chars().flat_map(char::to_lowercase);
+            let b = b.chars().flat_map(char::to_lowercase);
+            a.cmp(b)
+        }
+        _ => a.cmp(b),
+    }
+}
+
 /// Order `a` and `b` lexicographically using `Ord`
 pub fn iter_cmp<A, L, R>(mut a: L, mut b: R) -> cmp::Ordering
         where A: Ord, L: Iterator<Item=A>, R: Iterator<Item=A> {
@@ -145,8 +761,6 @@ fn compare_float(f1: f64, f2: f64) -> cmp::Ordering {
     f1.partial_cmp(&f2).unwrap_or(cmp::Ordering::Equal)
 }
 
-
-
 fn next_num<'a, X>(xs: &mut X) -> Option<Number>
         where X: Iterator<Item=&'a [u8]> {
     xs.next()
@@ -1,4 +1,5 @@
 use std::borrow::ToOwned;
+use std::collections::HashMap;
 use std::default::Default;
 use std::fmt;
 use std::fs;
@@ -7,7 +8,10 @@ use std::iter::{FromIterator, repeat};
 use std::str::{self, FromStr};
 
 use channel;
+use chrono::{DateTime, NaiveDate, NaiveDateTime};
 use csv;
+use serde::de::{Deserializer, Deserialize, Error};
+use serde_json::{self, Map, Number, Value};
 use stats::{Commute, OnlineStats, MinMax, Unsorted, merge_all};
 use threadpool::ThreadPool;
 
@@ -15,9 +19,12 @@ use CliResult;
 use config::{Config, Delimiter};
 use index::Indexed;
 use select::{SelectColumns, Selection};
-use util;
+use sketch::{HyperLogLog, TDigest};
+use util::{self, ByteString};
 
-use self::FieldType::{TUnknown, TNull, TUnicode, TFloat, TInteger};
+use self::FieldType::{
+    TUnknown, TNull, TUnicode, TFloat, TInteger, TDate, TDateTime, TBoolean,
+};
 
 static USAGE: &'static str = "
 Computes basic statistics on CSV data.
@@ -29,9 +36,39 @@ be enabled explicitly. By default, the following statistics are reported for
 default set of statistics corresponds to statistics that can be computed
 efficiently on a stream of data (i.e., constant memory).
 
+The type of each column is inferred from its values. Besides numbers and
+strings, columns of dates and date times are detected, in which case min and
+max are chronological and written in ISO 8601 format. Date times with a UTC
+offset are converted to UTC. Columns of tokens like 'yes' and 'no' are
+booleans, for which the number and share of true values can be reported with
+--booleans.
+
+With --histogram, a histogram of each numeric column is shown instead of the
+statistics. It is formatted as CSV data:
+
+    field,lower,upper,count
+
+where each bin includes its lower bound, and the last bin also includes its
+upper bound. The bins either have the same width between the min and max of
+the column, or are bounded by quantiles so that they hold about the same number
+of values. With --bars, the histograms are drawn as ASCII bar charts instead.
+Since the bins depend on the whole column, the input is read twice, which
+means that <stdin> cannot be used.
+
+With --format json, the statistics are written as a JSON array with one object
+per column instead, keyed by the names of the statistics. Statistics are
+numbers, except for the field, its type (e.g., 'Integer'), and the min, max
+and mode of columns that aren't numeric. Statistics that don't apply are
+null.
+
 Computing statistics on a large file can be made much faster if you create
 an index for it first with 'xsv index'.
 
+With --group-by, the statistics are computed separately for each value of the
+given column, in the order in which the values first appear. Each row of the
+output then starts with the value of the group. (The statistics of the group
+column itself are included too, unless it is excluded with --select.)
+
 Usage:
     xsv stats [options] [<input>]
 
@@ -40,15 +77,70 @@ stats options:
                            See 'xsv select --help' for the format details.
                            This is provided here because piping 'xsv select'
                            into 'xsv stats' will disable the use of indexing.
-    --everything           Show all statistics available.
+    --everything           Show all statistics available, except for the
+                           ones that must be asked for explicitly: those of
+                           the quantiles, moments, counts and booleans
+                           flags.
     --mode                 Show the mode.
                            This requires storing all CSV data in memory.
     --cardinality          Show the cardinality.
                            This requires storing all CSV data in memory.
     --median               Show the median.
                            This requires storing all CSV data in memory.
+    --quantiles <list>     Show the given quantiles, a comma separated list
+                           of numbers between 0 and 1, e.g., '0.25,0.5,0.9'.
+                           There is one column for each quantile, named like
+                           'q0.9'. When both 0.25 and 0.75 are given, the
+                           interquartile range is shown as 'iqr' too.
+                           This requires storing all CSV data in memory,
+                           unless --approx is given.
+    --approx               Estimate the median, quantiles and cardinality
+                           using constant memory instead of computing them
+                           exactly. Quantiles are estimated with a t-digest,
+                           which is usually very close, especially for
+                           extreme quantiles like 0.99. The cardinality is
+                           estimated with HyperLogLog, whose error depends on
+                           --precision.
+    --precision <p>        The precision of the HyperLogLog used by --approx,
+                           between 4 and 18. It uses 2^p bytes per column
+                           and has a standard error of about
+                           1.04 / sqrt(2^p), e.g., 0.8% for 14.
+                           [default: 14]
+    --date-formats <list>  A comma separated list of additional formats used
+                           to detect dates, in strftime syntax, e.g.,
+                           '%d/%m/%Y,%d %b %Y %H:%M'. ISO 8601 dates and
+                           date times, as well as '%Y/%m/%d', are always
+                           detected.
+    --true-values <list>   A comma separated list of the values that are
+                           true in boolean columns. Case is ignored.
+                           [default: true,yes,y]
+    --false-values <list>  A comma separated list of the values that are
+                           false in boolean columns. Case is ignored. When
+                           numbers are used, e.g., '1' and '0', columns that
+                           mix them with other numbers are Unicode.
+                           [default: false,no,n]
+    --histogram <bins>     Show a histogram with the given number of bins for
+                           each numeric column instead of the statistics.
+    --bin-type <type>      How the bins of --histogram are chosen: 'width'
+                           for bins of equal width, or 'quantile' for bins
+                           bounded by quantiles. Quantiles are estimated
+                           when --approx is set.
+                           [default: width]
+    --bars                 Draw the histograms as ASCII bar charts.
+    --moments              Show the variance, skewness, (excess) kurtosis and
+                           coefficient of variation ('cv') of numbers. Like
+                           the standard deviation, these are population
+                           statistics.
+    --counts               Show the number of zeros and negative numbers, and
+                           the share of NULLs ('null_ratio').
+    --booleans             Show the number and share of true values in
+                           boolean columns ('true_count' and 'true_ratio').
     --nulls                Include NULLs in the population size for computing
                            mean and standard deviation.
+    -g, --group-by <col>   Compute statistics for each value of the given
+                           column separately.
+    --format <format>      The output format: 'csv' or 'json'.
+                           [default: csv]
     -j, --jobs <arg>       The number of jobs to run in parallel.
                            This works better when the given CSV data has
                            an index already created. Note that a file handle
@@ -75,7 +167,21 @@ struct Args {
     flag_mode: bool,
     flag_cardinality: bool,
     flag_median: bool,
+    flag_moments: bool,
+    flag_counts: bool,
+    flag_booleans: bool,
+    flag_quantiles: Option<QuantileList>,
+    flag_approx: bool,
+    flag_precision: usize,
+    flag_date_formats: Option<String>,
+    flag_true_values: String,
+    flag_false_values: String,
+    flag_histogram: Option<usize>,
+    flag_bin_type: String,
+    flag_bars: bool,
     flag_nulls: bool,
+    flag_group_by: Option<SelectColumns>,
+    flag_format: String,
     flag_jobs: usize,
     flag_output: Option<String>,
     flag_no_headers: bool,
@@ -84,9 +190,32 @@ struct Args {
 
 pub fn run(argv: &[&str]) -> CliResult<()> {
     let args: Args = util::get_args(USAGE, argv)?;
+    HyperLogLog::check_precision(args.flag_precision)?;
+    if args.flag_format != "csv" && args.flag_format != "json" {
+        return fail!(format!(
+            "Unknown format '{}'. Expected 'csv' or 'json'.",
+            args.flag_format));
+    }
+    if args.flag_format == "json" && args.flag_histogram.is_some() {
+        return fail!("--format json cannot be used with --histogram.");
+    }
+    if let Some(bins) = args.flag_histogram {
+        if bins == 0 {
+            return fail!("--histogram must have at least one bin.");
+        }
+        if args.flag_bin_type != "width" && args.flag_bin_type != "quantile" {
+            return fail!(format!(
+                "Unknown bin type '{}'. Expected 'width' or 'quantile'.",
+                args.flag_bin_type));
+        }
+        if args.rconfig().is_std() {
+            return fail!("<stdin> cannot be used with --histogram, since \
+                          the input is read twice. Please specify a file \
+                          path.");
+        }
+    }
 
-    let mut wtr = Config::new(&args.flag_output).writer()?;
-    let (headers, stats) = match args.rconfig().indexed()? {
+    let (headers, group_header, groups) = match args.rconfig().indexed()? {
         None => args.sequential_stats(),
         Some(idx) => {
             if args.flag_jobs == 1 {
@@ -96,36 +225,71 @@ pub fn run(argv: &[&str]) -> CliResult<()> {
             }
         }
     }?;
-    let stats = args.stats_to_records(stats);
-
-    wtr.write_record(&args.stat_headers())?;
-    let fields = headers.iter().zip(stats.into_iter());
-    for (i, (header, stat)) in fields.enumerate() {
-        let header =
-            if args.flag_no_headers {
-                i.to_string().into_bytes()
-            } else {
-                header.to_vec()
-            };
-        let stat = stat.iter().map(|f| f.as_bytes());
-        wtr.write_record(vec![&*header].into_iter().chain(stat))?;
+    if args.flag_histogram.is_some() {
+        return args.write_histograms(headers, group_header, groups);
+    }
+
+    let (keys, stats): (Vec<_>, Vec<_>) = groups.groups.into_iter().unzip();
+    let stats = args.stats_to_records(stats.into_iter().flat_map(Vec::into_iter));
+
+    let mut stat_headers: csv::ByteRecord = group_header.into_iter().collect();
+    stat_headers.extend(args.stat_headers().iter().map(|f| f.as_bytes()));
+    let mut rows = vec![];
+    let mut stats = stats.into_iter();
+    for key in keys {
+        let fields = headers.iter().zip(stats.by_ref());
+        for (i, (header, stat)) in fields.enumerate() {
+            let header =
+                if args.flag_no_headers {
+                    i.to_string().into_bytes()
+                } else {
+                    header.to_vec()
+                };
+            let key =
+                if args.flag_group_by.is_some() {
+                    Some(key.clone())
+                } else {
+                    None
+                };
+            rows.push(StatsRow { group: key, field: header, stats: stat });
+        }
+    }
+    if args.flag_format == "json" {
+        return args.write_json(&stat_headers, &rows);
+    }
+
+    let mut wtr = Config::new(&args.flag_output).writer()?;
+    wtr.write_record(&stat_headers)?;
+    for row in &rows {
+        let mut record = csv::ByteRecord::new();
+        if let Some(ref group) = row.group {
+            record.push_field(group);
+        }
+        record.push_field(&row.field);
+        for stat in &row.stats {
+            record.push_field(stat.to_string().as_bytes());
+        }
+        wtr.write_record(&record)?;
     }
     wtr.flush()?;
     Ok(())
 }
 
 impl Args {
-    fn sequential_stats(&self) -> CliResult<(csv::ByteRecord, Vec<Stats>)> {
+    fn sequential_stats(&self) -> CliResult<(
+        csv::ByteRecord, Option<ByteString>, GroupStats,
+    )> {
         let mut rdr = self.rconfig().reader()?;
-        let (headers, sel) = self.sel_headers(&mut rdr)?;
-        let stats = self.compute(&sel, rdr.byte_records())?;
-        Ok((headers, stats))
+        let (headers, sel, group) = self.sel_headers(&mut rdr)?;
+        let stats = self.compute(
+            &sel, group.as_ref().map(|g| g.0), rdr.byte_records())?;
+        Ok((headers, group.map(|g| g.1), stats))
     }
 
     fn parallel_stats(
         &self,
         idx: Indexed<fs::File, fs::File>,
-    ) -> CliResult<(csv::ByteRecord, Vec<Stats>)> {
+    ) -> CliResult<(csv::ByteRecord, Option<ByteString>, GroupStats)> {
         // N.B. This method doesn't handle the case when the number of records
         // is zero correctly. So we use `sequential_stats` instead.
         if idx.count() == 0 {
@@ -133,7 +297,8 @@ impl Args {
         }
 
         let mut rdr = self.rconfig().reader()?;
-        let (headers, sel) = self.sel_headers(&mut rdr)?;
+        let (headers, sel, group) = self.sel_headers(&mut rdr)?;
+        let group_idx = group.as_ref().map(|g| g.0);
 
         let chunk_size = util::chunk_size(idx.count() as usize, self.njobs());
         let nchunks = util::num_of_chunks(idx.count() as usize, chunk_size);
@@ -146,35 +311,245 @@ impl Args {
                 let mut idx = args.rconfig().indexed().unwrap().unwrap();
                 idx.seek((i * chunk_size) as u64).unwrap();
                 let it = idx.byte_records().take(chunk_size);
-                send.send(args.compute(&sel, it).unwrap());
+                send.send((i, args.compute(&sel, group_idx, it).unwrap()));
+            });
+        }
+        drop(send);
+
+        // Groups are written in order of first appearance, so the chunks
+        // are merged in the order they appear in the input.
+        let mut chunks: Vec<Option<GroupStats>> = vec![None; nchunks];
+        for (i, stats) in recv {
+            chunks[i] = Some(stats);
+        }
+        let mut chunks = chunks.into_iter().map(|c| c.unwrap());
+        let mut stats = chunks.next().unwrap();
+        for chunk in chunks {
+            stats.merge(chunk);
+        }
+        Ok((headers, group.map(|g| g.1), stats))
+    }
+
+    /// Count the numbers of each column in bins, whose edges are computed
+    /// from the stats of a first pass over the input.
+    fn write_histograms(
+        &self,
+        headers: csv::ByteRecord,
+        group_header: Option<ByteString>,
+        mut groups: GroupStats,
+    ) -> CliResult<()> {
+        let bins = self.flag_histogram.unwrap();
+        let by_quantile = self.flag_bin_type == "quantile";
+        let empty: Histograms = groups.groups.iter_mut().map(|g| {
+            g.1.iter_mut().map(|s| s.histogram(bins, by_quantile)).collect()
+        }).collect();
+        let index = groups.index;
+        let hists = match self.rconfig().indexed()? {
+            Some(ref idx) if self.flag_jobs != 1 && idx.count() > 0 => {
+                self.parallel_histograms(idx.count(), index, empty)
+            }
+            _ => self.sequential_histograms(&index, empty),
+        }?;
+
+        let keys = groups.groups.into_iter().map(|g| g.0);
+        if self.flag_bars {
+            let mut wtr = Config::new(&self.flag_output).io_writer()?;
+            for (key, hists) in keys.zip(hists) {
+                for (i, hist) in hists.into_iter().enumerate() {
+                    let hist = match hist {
+                        None => continue,
+                        Some(hist) => hist,
+                    };
+                    let mut title = self.field_name(&headers, i);
+                    if let Some(ref group) = group_header {
+                        title.extend_from_slice(b" (");
+                        title.extend_from_slice(group);
+                        title.push(b'=');
+                        title.extend_from_slice(&key);
+                        title.push(b')');
+                    }
+                    wtr.write_all(&title)?;
+                    wtr.write_all(b"\n")?;
+                    hist.write_bars(&mut wtr)?;
+                    wtr.write_all(b"\n")?;
+                }
+            }
+            wtr.flush()?;
+            return Ok(());
+        }
+
+        let mut wtr = Config::new(&self.flag_output).writer()?;
+        let mut hist_headers: csv::ByteRecord =
+            group_header.iter().collect();
+        hist_headers.extend(&["field", "lower", "upper", "count"]);
+        wtr.write_record(&hist_headers)?;
+        for (key, hists) in keys.zip(hists) {
+            for (i, hist) in hists.into_iter().enumerate() {
+                let hist = match hist {
+                    None => continue,
+                    Some(hist) => hist,
+                };
+                let field = self.field_name(&headers, i);
+                for (lower, upper, count) in hist.bins() {
+                    let mut record = vec![];
+                    if self.flag_group_by.is_some() {
+                        record.push(key.clone());
+                    }
+                    record.push(field.clone());
+                    record.push(show_edge(lower).into_bytes());
+                    record.push(show_edge(upper).into_bytes());
+                    record.push(count.to_string().into_bytes());
+                    wtr.write_record(&record)?;
+                }
+            }
+        }
+        wtr.flush()?;
+        Ok(())
+    }
+
+    fn sequential_histograms(
+        &self,
+        index: &HashMap<ByteString, usize>,
+        empty: Histograms,
+    ) -> CliResult<Histograms> {
+        let mut rdr = self.rconfig().reader()?;
+        let (_, sel, group) = self.sel_headers(&mut rdr)?;
+        let group = group.as_ref().map(|g| g.0);
+        self.count_bins(&sel, group, index, empty, rdr.byte_records())
+    }
+
+    fn parallel_histograms(
+        &self,
+        count: u64,
+        index: HashMap<ByteString, usize>,
+        empty: Histograms,
+    ) -> CliResult<Histograms> {
+        let mut rdr = self.rconfig().reader()?;
+        let (_, sel, group) = self.sel_headers(&mut rdr)?;
+        let group_idx = group.as_ref().map(|g| g.0);
+
+        let chunk_size = util::chunk_size(count as usize, self.njobs());
+        let nchunks = util::num_of_chunks(count as usize, chunk_size);
+
+        let pool = ThreadPool::new(self.njobs());
+        let (send, recv) = channel::bounded(0);
+        for i in 0..nchunks {
+            let (send, args, sel) = (send.clone(), self.clone(), sel.clone());
+            let (index, empty) = (index.clone(), empty.clone());
+            pool.execute(move || {
+                let mut idx = args.rconfig().indexed().unwrap().unwrap();
+                idx.seek((i * chunk_size) as u64).unwrap();
+                let it = idx.byte_records().take(chunk_size);
+                let hists =
+                    args.count_bins(&sel, group_idx, &index, empty, it);
+                send.send(hists.unwrap());
             });
         }
         drop(send);
-        Ok((headers, merge_all(recv).unwrap_or_else(Vec::new)))
+        Ok(merge_all(recv).unwrap_or(empty))
+    }
+
+    fn count_bins<I>(
+        &self,
+        sel: &Selection,
+        group: Option<usize>,
+        index: &HashMap<ByteString, usize>,
+        mut hists: Histograms,
+        it: I,
+    ) -> CliResult<Histograms>
+            where I: Iterator<Item=csv::Result<csv::ByteRecord>> {
+        for row in it {
+            let row = row?;
+            // Every group was seen by the first pass over the same input.
+            let gi = match group {
+                None => 0,
+                Some(g) => index[&row[g]],
+            };
+            for (hist, field) in hists[gi].iter_mut().zip(sel.select(&row)) {
+                if let Some(ref mut hist) = *hist {
+                    if let Some(n) = from_bytes::<f64>(field) {
+                        hist.add(n);
+                    }
+                }
+            }
+        }
+        Ok(hists)
+    }
+
+    /// Write one JSON object per row of statistics.
+    fn write_json(
+        &self,
+        headers: &csv::ByteRecord,
+        rows: &[StatsRow],
+    ) -> CliResult<()> {
+        let lossy = |s: &[u8]| String::from_utf8_lossy(s).into_owned();
+        let mut objects = vec![];
+        for row in rows {
+            let mut values = vec![];
+            if let Some(ref group) = row.group {
+                values.push(Value::String(lossy(group)));
+            }
+            values.push(Value::String(lossy(&row.field)));
+            values.extend(row.stats.iter().map(|stat| stat.to_json()));
+
+            let mut obj = Map::new();
+            for (name, value) in headers.iter().zip(values) {
+                obj.insert(lossy(name), value);
+            }
+            objects.push(Value::Object(obj));
+        }
+        let mut wtr = Config::new(&self.flag_output).io_writer()?;
+        serde_json::to_writer_pretty(&mut wtr, &objects)
+            .map_err(|e| e.to_string())?;
+        wtr.write_all(b"\n")?;
+        wtr.flush()?;
+        Ok(())
+    }
+
+    fn field_name(&self, headers: &csv::ByteRecord, i: usize) -> ByteString {
+        if self.flag_no_headers {
+            i.to_string().into_bytes()
+        } else {
+            headers[i].to_vec()
+        }
     }
 
-    fn stats_to_records(&self, stats: Vec<Stats>) -> Vec<csv::StringRecord> {
-        let mut records: Vec<_> = repeat(csv::StringRecord::new())
-            .take(stats.len())
-            .collect();
+    fn stats_to_records<I>(&self, stats: I) -> Vec<Vec<Stat>>
+            where I: IntoIterator<Item=Stats> {
         let pool = ThreadPool::new(self.njobs());
         let mut results = vec![];
         for mut stat in stats.into_iter() {
             let (send, recv) = channel::bounded(0);
             results.push(recv);
-            pool.execute(move || { send.send(stat.to_record()); });
+            pool.execute(move || { send.send(stat.to_stats()); });
         }
-        for (i, recv) in results.into_iter().enumerate() {
-            records[i] = recv.recv().unwrap();
-        }
-        records
+        results.into_iter().map(|recv| recv.recv().unwrap()).collect()
     }
 
-    fn compute<I>(&self, sel: &Selection, it: I) -> CliResult<Vec<Stats>>
+    fn compute<I>(
+        &self,
+        sel: &Selection,
+        group: Option<usize>,
+        it: I,
+    ) -> CliResult<GroupStats>
             where I: Iterator<Item=csv::Result<csv::ByteRecord>> {
-        let mut stats = self.new_stats(sel.len());
+        let mut stats = GroupStats::default();
+        if group.is_none() {
+            stats.insert(vec![], self.new_stats(sel.len()));
+        }
         for row in it {
             let row = row?;
+            let gi = match group {
+                None => 0,
+                Some(g) => match stats.index.get(&row[g]) {
+                    Some(&gi) => gi,
+                    None => {
+                        let new = self.new_stats(sel.len());
+                        stats.insert(row[g].to_vec(), new)
+                    }
+                },
+            };
+            let stats = &mut stats.groups[gi].1;
             for (i, field) in sel.select(&row).enumerate() {
                 stats[i].add(field);
             }
@@ -182,13 +557,32 @@ impl Args {
         Ok(stats)
     }
 
+    /// Returns the selected headers and their selection, along with the
+    /// index and name of the --group-by column.
     fn sel_headers<R: io::Read>(
         &self,
         rdr: &mut csv::Reader<R>,
-    ) -> CliResult<(csv::ByteRecord, Selection)> {
+    ) -> CliResult<(csv::ByteRecord, Selection, Option<GroupColumn>)> {
         let headers = rdr.byte_headers()?.clone();
         let sel = self.rconfig().selection(&headers)?;
-        Ok((csv::ByteRecord::from_iter(sel.select(&headers)), sel))
+        let group = match self.flag_group_by {
+            None => None,
+            Some(ref group_by) => {
+                let gsel =
+                    group_by.selection(&headers, !self.flag_no_headers)?;
+                if gsel.len() != 1 {
+                    return fail!("--group-by must select exactly one column.");
+                }
+                let name =
+                    if self.flag_no_headers {
+                        b"group".to_vec()
+                    } else {
+                        headers[gsel[0]].to_vec()
+                    };
+                Some((gsel[0], name))
+            }
+        };
+        Ok((csv::ByteRecord::from_iter(sel.select(&headers)), sel, group))
     }
 
     fn rconfig(&self) -> Config {
@@ -211,9 +605,30 @@ impl Args {
             cardinality: self.flag_cardinality || self.flag_everything,
             median: self.flag_median || self.flag_everything,
             mode: self.flag_mode || self.flag_everything,
+            moments: self.flag_moments,
+            counts: self.flag_counts,
+            booleans: self.flag_booleans,
+            quantiles: self.quantiles(),
+            approx: self.flag_approx,
+            precision: self.flag_precision as u8,
+            formats: Formats::new(&self.flag_date_formats,
+                                  &self.flag_true_values,
+                                  &self.flag_false_values),
         })).take(record_len).collect()
     }
 
+    fn quantiles(&self) -> Vec<f64> {
+        if let Some(bins) = self.flag_histogram {
+            if self.flag_bin_type == "quantile" {
+                return (1..bins).map(|i| i as f64 / bins as f64).collect();
+            }
+        }
+        match self.flag_quantiles {
+            None => vec![],
+            Some(ref list) => list.0.iter().map(|&(_, q)| q).collect(),
+        }
+    }
+
     fn stat_headers(&self) -> csv::StringRecord {
         let mut fields = vec![
             "field", "type", "sum", "min", "max", "min_length", "max_length",
@@ -223,11 +638,172 @@ impl Args {
         if self.flag_median || all { fields.push("median"); }
         if self.flag_mode || all { fields.push("mode"); }
         if self.flag_cardinality || all { fields.push("cardinality"); }
+        let mut fields: Vec<String> =
+            fields.into_iter().map(|f| f.to_owned()).collect();
+        if let Some(ref list) = self.flag_quantiles {
+            for &(ref name, _) in &list.0 {
+                fields.push(format!("q{}", name));
+            }
+            if list.has_iqr() {
+                fields.push("iqr".to_owned());
+            }
+        }
+        if self.flag_moments {
+            for &f in &["variance", "skewness", "kurtosis", "cv"] {
+                fields.push(f.to_owned());
+            }
+        }
+        if self.flag_counts {
+            for &f in &["zeros", "negatives", "null_ratio"] {
+                fields.push(f.to_owned());
+            }
+        }
+        if self.flag_booleans {
+            fields.push("true_count".to_owned());
+            fields.push("true_ratio".to_owned());
+        }
         csv::StringRecord::from(fields)
     }
 }
 
-#[derive(Clone, Debug, Eq, PartialEq)]
+/// The statistics of a column, or of a column in a group.
+struct StatsRow {
+    group: Option<ByteString>,
+    field: ByteString,
+    stats: Vec<Stat>,
+}
+
+/// A statistic of a column, which keeps its type so that it can be written
+/// as JSON as well as CSV.
+#[derive(Clone)]
+enum Stat {
+    /// A statistic that doesn't apply to the column.
+    Empty,
+    /// A mode that doesn't exist, since no value is the most frequent one.
+    NotAvailable,
+    Text(String),
+    /// A value of the column as written, e.g., its mode, which is a number
+    /// in JSON if the column is numeric.
+    Sample(String, FieldType),
+    Int(i64),
+    Float(f64),
+}
+
+impl Stat {
+    fn count(n: u64) -> Stat {
+        Stat::Int(n as i64)
+    }
+
+    fn float(n: Option<f64>) -> Stat {
+        n.map_or(Stat::Empty, Stat::Float)
+    }
+
+    fn to_json(&self) -> Value {
+        match *self {
+            Stat::Empty | Stat::NotAvailable => Value::Null,
+            Stat::Text(ref s) | Stat::Sample(ref s, _) if s.is_empty() => {
+                Value::Null
+            }
+            Stat::Text(ref s) => Value::String(s.clone()),
+            Stat::Sample(ref s, typ) => {
+                let number = match typ {
+                    TInteger => s.parse::<i64>().ok().map(Stat::Int),
+                    TFloat => s.parse::<f64>().ok().map(Stat::Float),
+                    _ => None,
+                };
+                number.map_or_else(|| Value::String(s.clone()), |n| n.to_json())
+            }
+            Stat::Int(n) => Value::from(n),
+            // JSON has no NaN or infinity.
+            Stat::Float(n) => {
+                Number::from_f64(n).map_or(Value::Null, Value::Number)
+            }
+        }
+    }
+}
+
+impl fmt::Display for Stat {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Stat::Empty => Ok(()),
+            Stat::NotAvailable => write!(f, "N/A"),
+            Stat::Text(ref s) | Stat::Sample(ref s, _) => write!(f, "{}", s),
+            Stat::Int(n) => write!(f, "{}", n),
+            Stat::Float(n) => write!(f, "{}", n),
+        }
+    }
+}
+
+/// A list of quantiles given on the command line, e.g., `0.25,0.5,0.9`,
+/// along with their names as written.
+#[derive(Clone, Debug)]
+struct QuantileList(Vec<(String, f64)>);
+
+impl QuantileList {
+    fn has_iqr(&self) -> bool {
+        has_iqr(&self.0.iter().map(|&(_, q)| q).collect::<Vec<_>>())
+    }
+}
+
+impl<'de> Deserialize<'de> for QuantileList {
+    fn deserialize<D: Deserializer<'de>>(
+        d: D,
+    ) -> Result<QuantileList, D::Error> {
+        let s = String::deserialize(d)?;
+        let mut list = vec![];
+        for name in s.split(',').map(|name| name.trim()) {
+            match name.parse::<f64>() {
+                Ok(q) if q >= 0.0 && q <= 1.0 => {
+                    list.push((name.to_owned(), q));
+                }
+                _ => {
+                    return Err(D::Error::custom(format!(
+                        "Invalid quantile '{}'. Quantiles must be numbers \
+                         between 0 and 1.", name)));
+                }
+            }
+        }
+        Ok(QuantileList(list))
+    }
+}
+
+/// Returns true if the interquartile range should be shown for these
+/// quantiles.
+fn has_iqr(quantiles: &[f64]) -> bool {
+    quantiles.contains(&0.25) && quantiles.contains(&0.75)
+}
+
+/// The index and name of the --group-by column.
+type GroupColumn = (usize, ByteString);
+
+/// GroupStats holds the stats of each group of rows, in order of first
+/// appearance. Without --group-by, all rows belong to a single group.
+#[derive(Clone, Default)]
+struct GroupStats {
+    index: HashMap<ByteString, usize>,
+    groups: Vec<(ByteString, Vec<Stats>)>,
+}
+
+impl GroupStats {
+    fn insert(&mut self, key: ByteString, stats: Vec<Stats>) -> usize {
+        let i = self.groups.len();
+        self.index.insert(key.clone(), i);
+        self.groups.push((key, stats));
+        i
+    }
+
+    /// Merge the stats of rows that come after the rows of these stats.
+    fn merge(&mut self, other: GroupStats) {
+        for (key, stats) in other.groups {
+            match self.index.get(&key) {
+                Some(&i) => self.groups[i].1.merge(stats),
+                None => { self.insert(key, stats); }
+            }
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
 struct WhichStats {
     include_nulls: bool,
     sum: bool,
@@ -236,6 +812,13 @@ struct WhichStats {
     cardinality: bool,
     median: bool,
     mode: bool,
+    moments: bool,
+    counts: bool,
+    booleans: bool,
+    quantiles: Vec<f64>,
+    approx: bool,
+    precision: u8,
+    formats: Formats,
 }
 
 impl Commute for WhichStats {
@@ -252,6 +835,11 @@ struct Stats {
     online: Option<OnlineStats>,
     mode: Option<Unsorted<Vec<u8>>>,
     median: Option<Unsorted<f64>>,
+    quantiles: Option<Quantiles>,
+    hll: Option<HyperLogLog>,
+    bools: BoolCounts,
+    moments: Option<Moments>,
+    counts: Option<Counts>,
     which: WhichStats,
 }
 
@@ -262,8 +850,27 @@ impl Stats {
         if which.sum { sum = Some(Default::default()); }
         if which.range { minmax = Some(Default::default()); }
         if which.dist { online = Some(Default::default()); }
-        if which.mode || which.cardinality { mode = Some(Default::default()); }
-        if which.median { median = Some(Default::default()); }
+        let (mut moments, mut counts) = (None, None);
+        if which.moments { moments = Some(Default::default()); }
+        if which.counts { counts = Some(Default::default()); }
+        if which.mode || which.cardinality && !which.approx {
+            mode = Some(Default::default());
+        }
+        let hll =
+            if which.cardinality && which.approx {
+                Some(HyperLogLog::new(which.precision))
+            } else {
+                None
+            };
+        if which.median && !which.approx {
+            median = Some(Default::default());
+        }
+        let quantiles =
+            if !which.quantiles.is_empty() || which.median && which.approx {
+                Some(Quantiles::new(which.approx))
+            } else {
+                None
+            };
         Stats {
             typ: Default::default(),
             sum: sum,
@@ -271,18 +878,33 @@ impl Stats {
             online: online,
             mode: mode,
             median: median,
+            quantiles: quantiles,
+            hll: hll,
+            bools: Default::default(),
+            moments: moments,
+            counts: counts,
             which: which,
         }
     }
 
     fn add(&mut self, sample: &[u8]) {
-        let sample_type = FieldType::from_sample(sample);
+        let formats = &self.which.formats;
+        let sample_type = FieldType::from_sample(sample, formats);
         self.typ.merge(sample_type);
+        if sample_type == TBoolean {
+            let yes = str::from_utf8(sample)
+                .ok()
+                .and_then(|s| formats.parse_bool(s))
+                .unwrap();
+            self.bools.add(yes);
+        }
 
         let t = self.typ;
         self.sum.as_mut().map(|v| v.add(t, sample));
-        self.minmax.as_mut().map(|v| v.add(t, sample));
+        self.minmax.as_mut().map(|v| v.add(t, sample, formats));
         self.mode.as_mut().map(|v| v.add(sample.to_vec()));
+        self.hll.as_mut().map(|v| v.add(sample));
+        self.counts.as_mut().map(|v| v.add_sample(sample_type));
         match self.typ {
             TUnknown => {}
             TNull => {
@@ -290,7 +912,7 @@ impl Stats {
                     self.online.as_mut().map(|v| { v.add_null(); });
                 }
             }
-            TUnicode => {}
+            TUnicode | TDate | TDateTime | TBoolean => {}
             TFloat | TInteger => {
                 if sample_type.is_null() {
                     if self.which.include_nulls {
@@ -298,74 +920,168 @@ impl Stats {
                     }
                 } else {
                     let n = from_bytes::<f64>(sample).unwrap();
-                    self.median.as_mut().map(|v| { v.add(n); });
+                    // NaN has no place in the order of the numbers, so it
+                    // is left out of the median and quantiles.
+                    if !n.is_nan() {
+                        self.median.as_mut().map(|v| { v.add(n); });
+                        self.quantiles.as_mut().map(|v| { v.add(n); });
+                    }
                     self.online.as_mut().map(|v| { v.add(n); });
+                    self.moments.as_mut().map(|v| { v.add(n); });
+                    self.counts.as_mut().map(|v| { v.add_number(n); });
                 }
             }
         }
     }
 
-    fn to_record(&mut self) -> csv::StringRecord {
+    fn to_stats(&mut self) -> Vec<Stat> {
         let typ = self.typ;
         let mut pieces = vec![];
-        let empty = || "".to_owned();
 
-        pieces.push(self.typ.to_string());
-        match self.sum.as_ref().and_then(|sum| sum.show(typ)) {
+        pieces.push(Stat::Text(self.typ.to_string()));
+        match self.sum.as_ref().and_then(|sum| sum.value(typ)) {
             Some(sum) => { pieces.push(sum); }
-            None => { pieces.push(empty()); }
+            None => { pieces.push(Stat::Empty); }
         }
-        match self.minmax.as_ref().and_then(|mm| mm.show(typ)) {
+        match self.minmax.as_ref().and_then(|mm| mm.values(typ)) {
             Some(mm) => { pieces.push(mm.0); pieces.push(mm.1); }
-            None => { pieces.push(empty()); pieces.push(empty()); }
+            None => { pieces.push(Stat::Empty); pieces.push(Stat::Empty); }
         }
         match self.minmax.as_ref().and_then(|mm| mm.len_range()) {
-            Some(mm) => { pieces.push(mm.0); pieces.push(mm.1); }
-            None => { pieces.push(empty()); pieces.push(empty()); }
+            Some((min, max)) => {
+                pieces.push(Stat::count(min as u64));
+                pieces.push(Stat::count(max as u64));
+            }
+            None => { pieces.push(Stat::Empty); pieces.push(Stat::Empty); }
         }
 
         if !self.typ.is_number() {
-            pieces.push(empty()); pieces.push(empty());
+            pieces.push(Stat::Empty); pieces.push(Stat::Empty);
         } else {
             match self.online {
                 Some(ref v) => {
-                    pieces.push(v.mean().to_string());
-                    pieces.push(v.stddev().to_string());
+                    pieces.push(Stat::Float(v.mean()));
+                    pieces.push(Stat::Float(v.stddev()));
                 }
-                None => { pieces.push(empty()); pieces.push(empty()); }
+                None => { pieces.push(Stat::Empty); pieces.push(Stat::Empty); }
             }
         }
-        match self.median.as_mut().and_then(|v| v.median()) {
+        let median = match self.quantiles {
+            Some(ref mut q) if self.which.approx && self.which.median => {
+                q.quantile(0.5)
+            }
+            _ => self.median.as_mut().and_then(|v| v.median()),
+        };
+        match median {
             None => {
                 if self.which.median {
-                    pieces.push(empty());
+                    pieces.push(Stat::Empty);
                 }
             }
-            Some(v) => { pieces.push(v.to_string()); }
+            Some(v) => { pieces.push(Stat::Float(v)); }
         }
         match self.mode.as_mut() {
             None => {
                 if self.which.mode {
-                    pieces.push(empty());
-                }
-                if self.which.cardinality {
-                    pieces.push(empty());
+                    pieces.push(Stat::Empty);
                 }
             }
             Some(ref mut v) => {
                 if self.which.mode {
-                    let lossy = |s: Vec<u8>| -> String {
-                        String::from_utf8_lossy(&*s).into_owned()
-                    };
                     pieces.push(
-                        v.mode().map_or("N/A".to_owned(), lossy));
+                        v.mode().map_or(Stat::NotAvailable, |v| {
+                            let v = String::from_utf8_lossy(&v).into_owned();
+                            Stat::Sample(v, typ)
+                        }));
+                }
+            }
+        }
+        if self.which.cardinality {
+            let cardinality = match (self.hll.as_ref(), self.mode.as_mut()) {
+                (Some(hll), _) => Some(hll.count()),
+                (None, Some(v)) => Some(v.cardinality() as u64),
+                (None, None) => None,
+            };
+            pieces.push(cardinality.map_or(Stat::Empty, Stat::count));
+        }
+        if !self.which.quantiles.is_empty() {
+            let qs = self.which.quantiles.clone();
+            let values: Vec<Option<f64>> = match self.quantiles {
+                Some(ref mut v) if self.typ.is_number() => {
+                    qs.iter().map(|&q| v.quantile(q)).collect()
                 }
-                if self.which.cardinality {
-                    pieces.push(v.cardinality().to_string());
+                _ => vec![None; qs.len()],
+            };
+            for v in &values {
+                pieces.push(Stat::float(*v));
+            }
+            if has_iqr(&qs) {
+                let q1 = values[qs.iter().position(|&q| q == 0.25).unwrap()];
+                let q3 = values[qs.iter().position(|&q| q == 0.75).unwrap()];
+                match (q1, q3) {
+                    (Some(q1), Some(q3)) => pieces.push(Stat::Float(q3 - q1)),
+                    _ => pieces.push(Stat::Empty),
                 }
             }
         }
-        csv::StringRecord::from(pieces)
+        if let Some(ref v) = self.moments {
+            if self.typ.is_number() {
+                pieces.push(Stat::float(v.variance()));
+                pieces.push(Stat::float(v.skewness()));
+                pieces.push(Stat::float(v.kurtosis()));
+                pieces.push(Stat::float(v.cv()));
+            } else {
+                pieces.extend(repeat(Stat::Empty).take(4));
+            }
+        }
+        if let Some(ref v) = self.counts {
+            if self.typ.is_number() {
+                pieces.push(Stat::count(v.zeros));
+                pieces.push(Stat::count(v.negatives));
+            } else {
+                pieces.push(Stat::Empty);
+                pieces.push(Stat::Empty);
+            }
+            pieces.push(Stat::float(v.null_ratio()));
+        }
+        if self.which.booleans {
+            match self.bools.values(typ) {
+                Some(b) => { pieces.push(b.0); pieces.push(b.1); }
+                None => { pieces.push(Stat::Empty); pieces.push(Stat::Empty); }
+            }
+        }
+        pieces
+    }
+}
+
+impl Stats {
+    /// Returns an empty histogram with the given number of bins, or `None`
+    /// if the column isn't numeric.
+    fn histogram(
+        &mut self,
+        bins: usize,
+        by_quantile: bool,
+    ) -> Option<Histogram> {
+        if !self.typ.is_number() {
+            return None;
+        }
+        let (min, max) = self.minmax.as_ref().and_then(|v| v.number_range())?;
+        let mut edges = vec![min];
+        if bins == 1 {
+            // A single bin has no inner edges, and no quantiles to find them.
+        } else if by_quantile {
+            let quantiles = self.quantiles.as_mut().unwrap();
+            for i in 1..bins {
+                // Infinities can be quantiles, but the bins stay finite.
+                let q = quantiles.quantile(i as f64 / bins as f64)?;
+                edges.push(q.max(min).min(max));
+            }
+        } else {
+            let width = (max - min) / bins as f64;
+            edges.extend((1..bins).map(|i| min + width * i as f64));
+        }
+        edges.push(max);
+        Some(Histogram::new(edges))
     }
 }
 
@@ -377,21 +1093,171 @@ impl Commute for Stats {
         self.online.merge(other.online);
         self.mode.merge(other.mode);
         self.median.merge(other.median);
+        self.quantiles.merge(other.quantiles);
+        self.hll.merge(other.hll);
+        self.bools.merge(other.bools);
+        self.moments.merge(other.moments);
+        self.counts.merge(other.counts);
         self.which.merge(other.which);
     }
 }
 
+type Histograms = Vec<Vec<Option<Histogram>>>;
+
+/// Histogram counts numbers in bins. The bins are given by their edges, and
+/// each bin includes its lower edge. The last bin includes its upper edge
+/// too.
+#[derive(Clone)]
+struct Histogram {
+    edges: Vec<f64>,
+    counts: Vec<u64>,
+}
+
+impl Histogram {
+    fn new(mut edges: Vec<f64>) -> Histogram {
+        // Quantiles can coincide, and a column can have a single value.
+        edges.dedup();
+        if edges.len() == 1 {
+            let edge = edges[0];
+            edges.push(edge);
+        }
+        let counts = vec![0; edges.len() - 1];
+        Histogram { edges: edges, counts: counts }
+    }
+
+    fn add(&mut self, n: f64) {
+        let last = self.counts.len() - 1;
+        if !n.is_finite() || n < self.edges[0] || n > self.edges[last + 1] {
+            return;
+        }
+        // Find the last bin whose lower edge is at most `n`. There are
+        // few enough bins that a linear scan is fine.
+        let inner = &self.edges[1..last + 1];
+        let i = inner.iter().take_while(|&&e| e <= n).count();
+        self.counts[i] += 1;
+    }
+
+    /// Returns the lower edge, upper edge and count of each bin.
+    fn bins<'a>(&'a self) -> Box<Iterator<Item=(f64, f64, u64)> + 'a> {
+        Box::new(self.edges.windows(2)
+                           .zip(&self.counts)
+                           .map(|(e, &c)| (e[0], e[1], c)))
+    }
+
+    fn write_bars<W: io::Write>(&self, wtr: &mut W) -> io::Result<()> {
+        const WIDTH: f64 = 50.0;
+
+        let last = self.counts.len() - 1;
+        let labels: Vec<String> = self.bins().enumerate().map(|(i, b)| {
+            let close = if i == last { "]" } else { ")" };
+            format!("[{}, {}{}", show_edge(b.0), show_edge(b.1), close)
+        }).collect();
+        let label_width = labels.iter().map(|l| l.len()).max().unwrap();
+        let max_count = *self.counts.iter().max().unwrap();
+        let count_width = max_count.to_string().len();
+        for (label, &count) in labels.iter().zip(&self.counts) {
+            // Round up, so that every non-empty bin has a bar.
+            let len = (count as f64 * WIDTH / max_count as f64).ceil();
+            let bar = "#".repeat(len as usize);
+            writeln!(wtr, "{:<lw$} {:>cw$} |{}",
+                     label, count, bar, lw = label_width, cw = count_width)?;
+        }
+        Ok(())
+    }
+}
+
+impl Commute for Histogram {
+    fn merge(&mut self, other: Histogram) {
+        for (c1, c2) in self.counts.iter_mut().zip(other.counts) {
+            *c1 += c2;
+        }
+    }
+}
+
+/// Show a bin edge, rounding away the noise of computing it.
+pub fn show_edge(edge: f64) -> String {
+    ((edge * 1e6).round() / 1e6).to_string()
+}
+
+/// Quantiles computes quantiles of numbers, either exactly by storing all
+/// of them or approximately with a t-digest.
+#[derive(Clone)]
+enum Quantiles {
+    Exact { samples: Vec<f64>, sorted: bool },
+    Approx(TDigest),
+}
+
+impl Quantiles {
+    fn new(approx: bool) -> Quantiles {
+        if approx {
+            Quantiles::Approx(TDigest::default())
+        } else {
+            Quantiles::Exact { samples: vec![], sorted: true }
+        }
+    }
+
+    fn add(&mut self, sample: f64) {
+        match *self {
+            Quantiles::Exact { ref mut samples, ref mut sorted } => {
+                samples.push(sample);
+                *sorted = false;
+            }
+            Quantiles::Approx(ref mut digest) => digest.add(sample),
+        }
+    }
+
+    /// Returns the `q`th quantile, interpolating linearly between the
+    /// closest ranks like the median does.
+    fn quantile(&mut self, q: f64) -> Option<f64> {
+        match *self {
+            Quantiles::Exact { ref mut samples, ref mut sorted } => {
+                if samples.is_empty() {
+                    return None;
+                }
+                if !*sorted {
+                    samples.sort_by(|a, b| a.partial_cmp(b).unwrap());
+                    *sorted = true;
+                }
+                let rank = q * (samples.len() - 1) as f64;
+                let (lo, hi) = (rank.floor() as usize, rank.ceil() as usize);
+                let frac = rank - lo as f64;
+                Some(samples[lo] + (samples[hi] - samples[lo]) * frac)
+            }
+            Quantiles::Approx(ref mut digest) => digest.quantile(q),
+        }
+    }
+}
+
+impl Commute for Quantiles {
+    fn merge(&mut self, other: Quantiles) {
+        match (self, other) {
+            (&mut Quantiles::Exact { ref mut samples, ref mut sorted },
+             Quantiles::Exact { samples: other, .. }) => {
+                samples.extend(other);
+                *sorted = false;
+            }
+            (&mut Quantiles::Approx(ref mut d1), Quantiles::Approx(d2)) => {
+                d1.merge(d2);
+            }
+            _ => unreachable!("cannot merge exact and approximate quantiles"),
+        }
+    }
+}
+
 #[derive(Clone, Copy, PartialEq)]
-enum FieldType {
+pub enum FieldType {
     TUnknown,
     TNull,
     TUnicode,
     TFloat,
     TInteger,
+    TDate,
+    TDateTime,
+    TBoolean,
 }
 
 impl FieldType {
-    fn from_sample(sample: &[u8]) -> FieldType {
+    pub fn from_sample(sample: &[u8], formats: &Formats) -> FieldType {
         if sample.is_empty() {
             return TNull;
         }
@@ -399,16 +1265,18 @@ impl FieldType {
             Err(_) => return TUnknown,
             Ok(s) => s,
         };
+        if formats.parse_bool(string).is_some() { return TBoolean; }
         if let Ok(_) = string.parse::<i64>() { return TInteger; }
         if let Ok(_) = string.parse::<f64>() { return TFloat; }
+        if let Some((typ, _)) = formats.parse_date(string) { return typ; }
         TUnicode
     }
 
-    fn is_number(&self) -> bool {
+    pub fn is_number(&self) -> bool {
         *self == TFloat || *self == TInteger
     }
 
-    fn is_null(&self) -> bool {
+    pub fn is_null(&self) -> bool {
         *self == TNull
     }
 }
@@ -419,6 +1287,9 @@ impl Commute for FieldType {
             (TUnicode, TUnicode) => TUnicode,
             (TFloat, TFloat) => TFloat,
             (TInteger, TInteger) => TInteger,
+            (TDate, TDate) => TDate,
+            (TDateTime, TDateTime) => TDateTime,
+            (TBoolean, TBoolean) => TBoolean,
             // Null does not impact the type.
             (TNull, any) | (any, TNull) => any,
             // There's no way to get around an unknown.
@@ -428,6 +1299,13 @@ impl Commute for FieldType {
             // Numbers can degrade to Unicode strings.
             (TUnicode, TFloat) | (TFloat, TUnicode) => TUnicode,
             (TUnicode, TInteger) | (TInteger, TUnicode) => TUnicode,
+            // Dates are date times at midnight.
+            (TDate, TDateTime) | (TDateTime, TDate) => TDateTime,
+            // Dates mixed with anything else degrade to Unicode strings.
+            (TDate, _) | (_, TDate) => TUnicode,
+            (TDateTime, _) | (_, TDateTime) => TUnicode,
+            // So do booleans, even when their tokens are numbers.
+            (TBoolean, _) | (_, TBoolean) => TUnicode,
         };
     }
 }
@@ -447,21 +1325,125 @@ impl fmt::Display for FieldType {
             TUnicode => write!(f, "Unicode"),
             TFloat => write!(f, "Float"),
             TInteger => write!(f, "Integer"),
+            TDate => write!(f, "Date"),
+            TDateTime => write!(f, "DateTime"),
+            TBoolean => write!(f, "Boolean"),
         }
     }
 }
 
+/// Date formats that are always detected, besides RFC 3339 date times.
+static DATE_FORMATS: &'static [&'static str] = &["%Y-%m-%d", "%Y/%m/%d"];
+static DATETIME_FORMATS: &'static [&'static str] = &[
+    "%Y-%m-%dT%H:%M:%S%.f",
+    "%Y-%m-%d %H:%M:%S%.f",
+    "%Y-%m-%dT%H:%M",
+    "%Y-%m-%d %H:%M",
+    "%Y/%m/%d %H:%M:%S%.f",
+    "%Y/%m/%d %H:%M",
+];
+
+/// Formats detects dates, date times and booleans in samples.
+///
+/// ISO 8601 and a few other common date formats are always detected.
+/// Additional formats use the strftime syntax of `chrono`. A format that
+/// doesn't include a time detects dates, and any other format detects date
+/// times. Booleans are detected with lists of true and false tokens, whose
+/// case is ignored.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Formats {
+    dates: Vec<String>,
+    trues: Vec<String>,
+    falses: Vec<String>,
+}
+
+impl Formats {
+    /// Create formats from comma separated lists of additional date
+    /// formats and of true and false tokens.
+    pub fn new(dates: &Option<String>, trues: &str, falses: &str) -> Formats {
+        let dates = match *dates {
+            None => vec![],
+            Some(ref dates) => split_list(dates),
+        };
+        Formats {
+            dates: dates,
+            trues: split_list(trues),
+            falses: split_list(falses),
+        }
+    }
+
+    /// Parse `s` as a boolean token.
+    pub fn parse_bool(&self, s: &str) -> Option<bool> {
+        if self.trues.iter().any(|t| t.eq_ignore_ascii_case(s)) {
+            Some(true)
+        } else if self.falses.iter().any(|t| t.eq_ignore_ascii_case(s)) {
+            Some(false)
+        } else {
+            None
+        }
+    }
+
+    /// Parse `s` as a date or a date time, returning its type (`TDate` or
+    /// `TDateTime`) and value. Date times with a UTC offset are converted
+    /// to UTC.
+    pub fn parse_date(&self, s: &str) -> Option<(FieldType, NaiveDateTime)> {
+        // All of the builtin formats start with a year, and are at least
+        // eight characters long (like 2018-1-5).
+        if s.len() >= 8 && s.as_bytes()[0].is_ascii_digit() {
+            if let Ok(dt) = DateTime::parse_from_rfc3339(s) {
+                return Some((TDateTime, dt.naive_utc()));
+            }
+            for f in DATETIME_FORMATS {
+                if let Ok(dt) = NaiveDateTime::parse_from_str(s, f) {
+                    return Some((TDateTime, dt));
+                }
+            }
+            for f in DATE_FORMATS {
+                if let Ok(d) = NaiveDate::parse_from_str(s, f) {
+                    return Some((TDate, midnight(d)));
+                }
+            }
+        }
+        for f in &self.dates {
+            if let Ok(dt) = NaiveDateTime::parse_from_str(s, f) {
+                return Some((TDateTime, dt));
+            }
+            if let Ok(d) = NaiveDate::parse_from_str(s, f) {
+                return Some((TDate, midnight(d)));
+            }
+        }
+        None
+    }
+}
+
+impl Default for Formats {
+    fn default() -> Formats {
+        Formats::new(&None, "true,yes,y", "false,no,n")
+    }
+}
+
+fn split_list(list: &str) -> Vec<String> {
+    list.split(',')
+        .map(|v| v.trim().to_owned())
+        .filter(|v| !v.is_empty())
+        .collect()
+}
+
+fn midnight(d: NaiveDate) -> NaiveDateTime {
+    d.and_hms_opt(0, 0, 0).unwrap()
+}
+
 /// TypedSum keeps a rolling sum of the data seen.
 ///
 /// It sums integers until it sees a float, at which point it sums floats.
 #[derive(Clone, Default)]
-struct TypedSum {
+pub struct TypedSum {
     integer: i64,
     float: Option<f64>,
 }
 
 impl TypedSum {
-    fn add(&mut self, typ: FieldType, sample: &[u8]) {
+    pub fn add(&mut self, typ: FieldType, sample: &[u8]) {
         if sample.is_empty() {
             return;
         }
@@ -488,11 +1470,17 @@ impl TypedSum {
         }
     }
 
-    fn show(&self, typ: FieldType) -> Option<String> {
+    pub fn show(&self, typ: FieldType) -> Option<String> {
+        self.value(typ).map(|sum| sum.to_string())
+    }
+
+    fn value(&self, typ: FieldType) -> Option<Stat> {
         match typ {
-            TNull | TUnicode | TUnknown  => None,
-            TInteger => Some(self.integer.to_string()),
-            TFloat => Some(self.float.unwrap_or(0.0).to_string()),
+            TNull | TUnicode | TUnknown | TDate | TDateTime | TBoolean => {
+                None
+            }
+            TInteger => Some(Stat::Int(self.integer)),
+            TFloat => Some(Stat::Float(self.float.unwrap_or(0.0))),
         }
     }
 }
@@ -511,28 +1499,39 @@ impl Commute for TypedSum {
 /// TypedMinMax keeps track of minimum/maximum values for each possible type
 /// where min/max makes sense.
 #[derive(Clone)]
-struct TypedMinMax {
+pub struct TypedMinMax {
     strings: MinMax<Vec<u8>>,
     str_len: MinMax<usize>,
     integers: MinMax<i64>,
     floats: MinMax<f64>,
+    /// The floats without infinities, which histograms can bin.
+    finite: MinMax<f64>,
+    dates: MinMax<NaiveDateTime>,
 }
 
 impl TypedMinMax {
-    fn add(&mut self, typ: FieldType, sample: &[u8]) {
+    pub fn add(
+        &mut self,
+        typ: FieldType,
+        sample: &[u8],
+        formats: &Formats,
+    ) {
         self.str_len.add(sample.len());
         if sample.is_empty() {
             return;
         }
         self.strings.add(sample.to_vec());
         match typ {
-            TUnicode | TUnknown | TNull => {}
+            TUnicode | TUnknown | TNull | TBoolean => {}
             TFloat => {
                 let n = str::from_utf8(&*sample)
                             .ok()
                             .and_then(|s| s.parse::<f64>().ok())
                             .unwrap();
                 self.floats.add(n);
+                if n.is_finite() {
+                    self.finite.add(n);
+                }
                 self.integers.add(n as i64);
             }
             TInteger => {
@@ -542,42 +1541,77 @@ impl TypedMinMax {
                             .unwrap();
                 self.integers.add(n);
                 self.floats.add(n as f64);
+                self.finite.add(n as f64);
+            }
+            TDate | TDateTime => {
+                let dt = str::from_utf8(&*sample)
+                             .ok()
+                             .and_then(|s| formats.parse_date(s))
+                             .unwrap()
+                             .1;
+                self.dates.add(dt);
             }
         }
     }
 
-    fn len_range(&self) -> Option<(String, String)> {
+    /// Returns the smallest and largest finite numbers seen.
+    pub fn number_range(&self) -> Option<(f64, f64)> {
+        match (self.finite.min(), self.finite.max()) {
+            (Some(&min), Some(&max)) => Some((min, max)),
+            _ => None,
+        }
+    }
+
+    pub fn len_range(&self) -> Option<(usize, usize)> {
         match (self.str_len.min(), self.str_len.max()) {
-            (Some(min), Some(max)) => Some((min.to_string(), max.to_string())),
+            (Some(&min), Some(&max)) => Some((min, max)),
             _ => None,
         }
     }
 
-    fn show(&self, typ: FieldType) -> Option<(String, String)> {
+    pub fn show(&self, typ: FieldType) -> Option<(String, String)> {
+        self.values(typ).map(|(min, max)| (min.to_string(), max.to_string()))
+    }
+
+    fn values(&self, typ: FieldType) -> Option<(Stat, Stat)> {
         match typ {
             TNull => None,
-            TUnicode | TUnknown => {
+            TUnicode | TUnknown | TBoolean => {
                 match (self.strings.min(), self.strings.max()) {
                     (Some(min), Some(max)) => {
                         let min = String::from_utf8_lossy(&**min).to_string();
                         let max = String::from_utf8_lossy(&**max).to_string();
-                        Some((min, max))
+                        Some((Stat::Text(min), Stat::Text(max)))
                     }
                     _ => None
                 }
             }
             TInteger => {
                 match (self.integers.min(), self.integers.max()) {
-                    (Some(min), Some(max)) => {
-                        Some((min.to_string(), max.to_string()))
+                    (Some(&min), Some(&max)) => {
+                        Some((Stat::Int(min), Stat::Int(max)))
                     }
                     _ => None
                 }
             }
             TFloat => {
                 match (self.floats.min(), self.floats.max()) {
+                    (Some(&min), Some(&max)) => {
+                        Some((Stat::Float(min), Stat::Float(max)))
+                    }
+                    _ => None
+                }
+            }
+            TDate | TDateTime => {
+                let format = if typ == TDate {
+                    "%Y-%m-%d"
+                } else {
+                    "%Y-%m-%dT%H:%M:%S%.f"
+                };
+                match (self.dates.min(), self.dates.max()) {
                     (Some(min), Some(max)) => {
-                        Some((min.to_string(), max.to_string()))
+                        Some((Stat::Text(min.format(format).to_string()),
+                              Stat::Text(max.format(format).to_string())))
                     }
                     _ => None
                 }
@@ -593,6 +1627,8 @@ impl Default for TypedMinMax {
             str_len: Default::default(),
             integers: Default::default(),
             floats: Default::default(),
+            finite: Default::default(),
+            dates: Default::default(),
         }
     }
 }
@@ -603,6 +1639,162 @@ impl Commute for TypedMinMax {
         self.str_len.merge(other.str_len);
         self.integers.merge(other.integers);
         self.floats.merge(other.floats);
+        self.finite.merge(other.finite);
+        self.dates.merge(other.dates);
+    }
+}
+
+/// BoolCounts counts the true and false values of a boolean column.
+#[derive(Clone, Default)]
+struct BoolCounts {
+    trues: u64,
+    falses: u64,
+}
+
+impl BoolCounts {
+    fn add(&mut self, yes: bool) {
+        if yes { self.trues += 1; } else { self.falses += 1; }
+    }
+
+    /// Returns the number and the share of true values.
+    fn values(&self, typ: FieldType) -> Option<(Stat, Stat)> {
+        let total = self.trues + self.falses;
+        if typ != TBoolean || total == 0 {
+            return None;
+        }
+        let ratio = self.trues as f64 / total as f64;
+        Some((Stat::count(self.trues), Stat::Float(ratio)))
+    }
+}
+
+impl Commute for BoolCounts {
+    fn merge(&mut self, other: BoolCounts) {
+        self.trues += other.trues;
+        self.falses += other.falses;
+    }
+}
+
+/// Moments computes the central moments of numbers in one pass, from
+/// which the variance, skewness and kurtosis follow.
+///
+/// The moments of two sets of numbers are combined with the formulas of
+/// Pébay, "Formulas for Robust, One-Pass Parallel Computation of
+/// Covariances and Arbitrary-Order Statistical Moments" (2008).
+#[derive(Clone, Default)]
+struct Moments {
+    n: f64,
+    mean: f64,
+    m2: f64,
+    m3: f64,
+    m4: f64,
+}
+
+impl Moments {
+    fn add(&mut self, n: f64) {
+        self.merge(Moments { n: 1.0, mean: n, m2: 0.0, m3: 0.0, m4: 0.0 });
+    }
+
+    fn variance(&self) -> Option<f64> {
+        if self.n == 0.0 { None } else { Some(self.m2 / self.n) }
+    }
+
+    fn skewness(&self) -> Option<f64> {
+        if self.m2 == 0.0 {
+            return None;
+        }
+        Some(self.n.sqrt() * self.m3 / self.m2.powf(1.5))
+    }
+
+    /// The excess kurtosis, which is `0` for a normal distribution.
+    fn kurtosis(&self) -> Option<f64> {
+        if self.m2 == 0.0 {
+            return None;
+        }
+        Some(self.n * self.m4 / (self.m2 * self.m2) - 3.0)
+    }
+
+    /// The coefficient of variation, i.e., the standard deviation divided
+    /// by the mean.
+    fn cv(&self) -> Option<f64> {
+        if self.n == 0.0 || self.mean == 0.0 {
+            return None;
+        }
+        Some(self.variance().unwrap().sqrt() / self.mean)
+    }
+}
+
+impl Commute for Moments {
+    fn merge(&mut self, other: Moments) {
+        if other.n == 0.0 {
+            return;
+        }
+        if self.n == 0.0 {
+            *self = other;
+            return;
+        }
+        let (na, nb) = (self.n, other.n);
+        let n = na + nb;
+        let d = other.mean - self.mean;
+        let (d2, d3, d4) = (d * d, d * d * d, d * d * d * d);
+
+        let m4 = self.m4 + other.m4
+            + d4 * na * nb * (na * na - na * nb + nb * nb) / (n * n * n)
+            + 6.0 * d2 * (na * na * other.m2 + nb * nb * self.m2) / (n * n)
+            + 4.0 * d * (na * other.m3 - nb * self.m3) / n;
+        let m3 = self.m3 + other.m3
+            + d3 * na * nb * (na - nb) / (n * n)
+            + 3.0 * d * (na * other.m2 - nb * self.m2) / n;
+        let m2 = self.m2 + other.m2 + d2 * na * nb / n;
+
+        self.n = n;
+        self.mean += d * nb / n;
+        self.m2 = m2;
+        self.m3 = m3;
+        self.m4 = m4;
+    }
+}
+
+/// Counts counts the values of a column that data quality checks often
+/// care about.
+#[derive(Clone, Default)]
+struct Counts {
+    total: u64,
+    nulls: u64,
+    zeros: u64,
+    negatives: u64,
+}
+
+impl Counts {
+    fn add_sample(&mut self, typ: FieldType) {
+        self.total += 1;
+        if typ.is_null() {
+            self.nulls += 1;
+        }
+    }
+
+    fn add_number(&mut self, n: f64) {
+        if n == 0.0 {
+            self.zeros += 1;
+        } else if n < 0.0 {
+            self.negatives += 1;
+        }
+    }
+
+    fn null_ratio(&self) -> Option<f64> {
+        if self.total == 0 {
+            None
+        } else {
+            Some(self.nulls as f64 / self.total as f64)
+        }
+    }
+}
+
+impl Commute for Counts {
+    fn merge(&mut self, other: Counts) {
+        self.total += other.total;
+        self.nulls += other.nulls;
+        self.zeros += other.zeros;
+        self.negatives += other.negatives;
     }
 }
 
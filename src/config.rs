@@ -10,11 +10,17 @@ use std::path::PathBuf;
 use csv;
 use index::Indexed;
 use serde::de::{Deserializer, Deserialize, Error};
+use stats::Commute;
 
 use CliResult;
+use cmd::stats::{FieldType, Formats};
 use select::{SelectColumns, Selection};
 use util;
 
+/// The number of rows that the types of columns are inferred from, for type
+/// selectors like ':numeric'.
+const TYPE_SAMPLE_ROWS: usize = 1000;
+
 
 #[derive(Clone, Copy, Debug)]
 pub struct Delimiter(pub u8);
@@ -59,7 +65,7 @@ pub struct Config {
     path: Option<PathBuf>, // None implies <stdin>
     idx_path: Option<PathBuf>,
     select_columns: Option<SelectColumns>,
-    delimiter: u8,
+    pub delimiter: u8,
     pub no_headers: bool,
     flexible: bool,
     terminator: csv::Terminator,
@@ -177,10 +183,38 @@ impl Config {
         match self.select_columns {
             None => Err("Config has no 'SelectColums'. Did you call \
                          Config::select?".to_owned()),
+            Some(ref sel) if sel.uses_types() => {
+                let types = self.column_types()?;
+                sel.typed_selection(
+                    first_record, !self.no_headers, Some(&types))
+            }
             Some(ref sel) => sel.selection(first_record, !self.no_headers),
         }
     }
 
+    /// Infers the type of each column from the first rows of the data, the
+    /// way 'xsv stats' does with its default options.
+    fn column_types(&self) -> Result<Vec<FieldType>, String> {
+        if self.is_std() {
+            return Err("Type selectors (e.g., ':numeric') cannot be used \
+                        with <stdin>, since the data is read twice. Please \
+                        specify a file path.".to_owned());
+        }
+        let mut rdr = self.reader_file().map_err(|err| err.to_string())?;
+        let formats = Formats::default();
+        let mut types: Vec<FieldType> = vec![];
+        for row in rdr.byte_records().take(TYPE_SAMPLE_ROWS) {
+            let row = row.map_err(|err| err.to_string())?;
+            if types.len() < row.len() {
+                types.resize(row.len(), FieldType::default());
+            }
+            for (typ, field) in types.iter_mut().zip(row.iter()) {
+                typ.merge(FieldType::from_sample(field, &formats));
+            }
+        }
+        Ok(types)
+    }
+
     pub fn write_headers<R: io::Read, W: io::Write>
                         (&self, r: &mut csv::Reader<R>, w: &mut csv::Writer<W>)
                         -> csv::Result<()> {
@@ -236,22 +270,32 @@ impl Config {
                 (fs::File::open(p)?, fs::File::open(ip)?)
             }
         };
-        // If the CSV data was last modified after the index file was last
-        // modified, then return an error and demand the user regenerate the
-        // index.
-        let data_modified = util::last_modified(&csv_file.metadata()?);
-        let idx_modified = util::last_modified(&idx_file.metadata()?);
-        if data_modified > idx_modified {
-            return Err(io::Error::new(
-                io::ErrorKind::Other,
-                "The CSV file was modified after the index file. \
-                 Please re-create the index.",
-            ));
-        }
+        check_index_modified(&csv_file, &idx_file)?;
         let csv_rdr = self.from_reader(csv_file);
         Ok(Some((csv_rdr, idx_file)))
     }
 
+    /// Open the value index of the columns in `sel`, if one was created
+    /// with `xsv index --columns`.
+    ///
+    /// Like with `index_files`, an error is returned if the index is older
+    /// than the CSV data.
+    pub fn value_index_file(
+        &self,
+        sel: &Selection,
+    ) -> io::Result<Option<fs::File>> {
+        let path = match self.path {
+            None => return Ok(None),
+            Some(ref p) => p,
+        };
+        let idx_file = match fs::File::open(&util::vidx_path(path, sel)) {
+            Err(_) => return Ok(None),
+            Ok(f) => f,
+        };
+        check_index_modified(&fs::File::open(path)?, &idx_file)?;
+        Ok(Some(idx_file))
+    }
+
     pub fn indexed(&self)
                   -> CliResult<Option<Indexed<fs::File, fs::File>>> {
         match self.index_files()? {
@@ -310,3 +354,21 @@ impl Config {
             .from_writer(wtr)
     }
 }
+
+/// If the CSV data was last modified after the index file was last modified,
+/// then return an error and demand the user regenerate the index.
+fn check_index_modified(
+    csv_file: &fs::File,
+    idx_file: &fs::File,
+) -> io::Result<()> {
+    let data_modified = util::last_modified(&csv_file.metadata()?);
+    let idx_modified = util::last_modified(&idx_file.metadata()?);
+    if data_modified > idx_modified {
+        return Err(io::Error::new(
+            io::ErrorKind::Other,
+            "The CSV file was modified after the index file. \
+             Please re-create the index.",
+        ));
+    }
+    Ok(())
+}
@@ -1,10 +1,15 @@
+use std::cmp::Ordering;
 use std::io;
 use std::ops;
+use std::str;
 
+use byteorder::{ReadBytesExt, WriteBytesExt, BigEndian};
 use csv;
 use csv_index::RandomAccessSimple;
 
 use CliResult;
+use select::Selection;
+use util::ByteString;
 
 /// Indexed composes a CSV reader with a simple random access index.
 pub struct Indexed<R, I> {
@@ -59,3 +64,181 @@ impl<R: io::Read + io::Seek, I: io::Read + io::Seek> Indexed<R, I> {
         Ok(())
     }
 }
+
+/// Write a value index of the CSV data in `rdr` to `wtr`.
+///
+/// A value index finds the records that have given values in the columns of
+/// `sel` without parsing all of the CSV data. Every record is indexed,
+/// including the header row, if there is one.
+///
+/// All numbers are big endian `u64`s. The index starts with the number of
+/// key columns and the number of records, which are followed by:
+///
+/// 1. the byte offset of each record, in the order of the data,
+/// 2. an entry for each record, made of its number and the position of its
+///    key in the next section, sorted by key (with the whitespace around
+///    each value trimmed, like `xsv join` does) and then by number,
+/// 3. the key of each entry, in the same order, as the length and bytes of
+///    each value as it appears in the data.
+///
+/// Since the entries are sorted, `ValueIndexReader` finds a key with a
+/// binary search.
+pub fn create_value_index<R: io::Read, W: io::Write>(
+    rdr: &mut csv::Reader<R>,
+    sel: &Selection,
+    mut wtr: W,
+) -> CliResult<()> {
+    let mut offsets = vec![];
+    let mut keys: Vec<(Vec<ByteString>, u64)> = vec![];
+    let mut row = csv::ByteRecord::new();
+    while rdr.read_byte_record(&mut row)? {
+        let key = sel.select(&row).map(|v| v.to_vec()).collect();
+        keys.push((key, offsets.len() as u64));
+        offsets.push(row.position().unwrap().byte());
+    }
+    keys.sort_by(|&(ref k1, n1), &(ref k2, n2)| {
+        cmp_trimmed(k1, k2).then(n1.cmp(&n2))
+    });
+
+    wtr.write_u64::<BigEndian>(sel.len() as u64)?;
+    wtr.write_u64::<BigEndian>(offsets.len() as u64)?;
+    for offset in offsets {
+        wtr.write_u64::<BigEndian>(offset)?;
+    }
+    let mut pos = 0;
+    for &(ref key, n) in &keys {
+        wtr.write_u64::<BigEndian>(n)?;
+        wtr.write_u64::<BigEndian>(pos)?;
+        pos += key.iter().map(|v| 8 + v.len() as u64).sum::<u64>();
+    }
+    for (key, _) in keys {
+        for value in key {
+            wtr.write_u64::<BigEndian>(value.len() as u64)?;
+            wtr.write_all(&value)?;
+        }
+    }
+    wtr.flush()?;
+    Ok(())
+}
+
+/// ValueIndexReader looks up records in a value index written by
+/// `create_value_index`.
+pub struct ValueIndexReader<R> {
+    rdr: R,
+    nkeys: u64,
+    count: u64,
+}
+
+impl<R: io::Read + io::Seek> ValueIndexReader<R> {
+    pub fn new(mut rdr: R) -> CliResult<ValueIndexReader<R>> {
+        rdr.seek(io::SeekFrom::Start(0))?;
+        let nkeys = rdr.read_u64::<BigEndian>()?;
+        let count = rdr.read_u64::<BigEndian>()?;
+        Ok(ValueIndexReader { rdr: rdr, nkeys: nkeys, count: count })
+    }
+
+    /// Return the number of records in the index, including the header
+    /// row.
+    pub fn count(&self) -> u64 {
+        self.count
+    }
+
+    /// Return the byte offset of record `i`.
+    pub fn offset(&mut self, i: u64) -> CliResult<u64> {
+        self.rdr.seek(io::SeekFrom::Start(16 + 8 * i))?;
+        Ok(self.rdr.read_u64::<BigEndian>()?)
+    }
+
+    /// Return the byte offsets of all records, in the order of the data.
+    pub fn offsets(&mut self) -> CliResult<Vec<u64>> {
+        self.rdr.seek(io::SeekFrom::Start(16))?;
+        let mut offsets = Vec::with_capacity(self.count as usize);
+        for _ in 0..self.count {
+            offsets.push(self.rdr.read_u64::<BigEndian>()?);
+        }
+        Ok(offsets)
+    }
+
+    /// Return the number and key of every record whose key is equal to
+    /// `key` once the whitespace around each value is trimmed, in the order
+    /// of the data.
+    pub fn find_trimmed<T: AsRef<[u8]>>(
+        &mut self,
+        key: &[T],
+    ) -> CliResult<Vec<(u64, Vec<ByteString>)>> {
+        // Find the first entry that isn't less than `key`.
+        let (mut lo, mut hi) = (0, self.count);
+        while lo < hi {
+            let mid = lo + (hi - lo) / 2;
+            let (_, k) = self.entry(mid)?;
+            if cmp_trimmed(&k, key) == Ordering::Less {
+                lo = mid + 1;
+            } else {
+                hi = mid;
+            }
+        }
+        let mut found = vec![];
+        for i in lo..self.count {
+            let (n, k) = self.entry(i)?;
+            if cmp_trimmed(&k, key) != Ordering::Equal {
+                break;
+            }
+            found.push((n, k));
+        }
+        Ok(found)
+    }
+
+    /// Return the number and key of every record, sorted by key.
+    pub fn entries(&mut self) -> CliResult<Vec<(u64, Vec<ByteString>)>> {
+        self.rdr.seek(io::SeekFrom::Start(self.entries_start()))?;
+        let mut numbers = Vec::with_capacity(self.count as usize);
+        for _ in 0..self.count {
+            numbers.push(self.rdr.read_u64::<BigEndian>()?);
+            self.rdr.read_u64::<BigEndian>()?;
+        }
+        // The keys are stored in the same order as the entries.
+        let mut entries = Vec::with_capacity(numbers.len());
+        for n in numbers {
+            entries.push((n, self.read_key()?));
+        }
+        Ok(entries)
+    }
+
+    fn entry(&mut self, i: u64) -> CliResult<(u64, Vec<ByteString>)> {
+        self.rdr.seek(io::SeekFrom::Start(self.entries_start() + 16 * i))?;
+        let n = self.rdr.read_u64::<BigEndian>()?;
+        let pos = self.rdr.read_u64::<BigEndian>()?;
+        let keys_start = self.entries_start() + 16 * self.count;
+        self.rdr.seek(io::SeekFrom::Start(keys_start + pos))?;
+        Ok((n, self.read_key()?))
+    }
+
+    fn entries_start(&self) -> u64 {
+        16 + 8 * self.count
+    }
+
+    fn read_key(&mut self) -> CliResult<Vec<ByteString>> {
+        let mut key = Vec::with_capacity(self.nkeys as usize);
+        for _ in 0..self.nkeys {
+            let len = self.rdr.read_u64::<BigEndian>()? as usize;
+            let mut value = vec![0; len];
+            self.rdr.read_exact(&mut value)?;
+            key.push(value);
+        }
+        Ok(key)
+    }
+}
+
+/// Compare two keys with the whitespace around each of their values
+/// trimmed. Values that aren't valid UTF-8 are compared as is.
+fn cmp_trimmed<A: AsRef<[u8]>, B: AsRef<[u8]>>(
+    k1: &[A],
+    k2: &[B],
+) -> Ordering {
+    fn trim(v: &[u8]) -> &[u8] {
+        str::from_utf8(v).map(|s| s.trim().as_bytes()).unwrap_or(v)
+    }
+    let k1 = k1.iter().map(|v| trim(v.as_ref()));
+    let k2 = k2.iter().map(|v| trim(v.as_ref()));
+    k1.cmp(k2)
+}
@@ -1,4 +1,5 @@
 extern crate byteorder;
+extern crate chrono;
 extern crate crossbeam_channel as channel;
 extern crate csv;
 extern crate csv_index;
@@ -10,9 +11,11 @@ extern crate regex;
 extern crate serde;
 #[macro_use]
 extern crate serde_derive;
+extern crate serde_json;
 extern crate stats;
 extern crate tabwriter;
 extern crate threadpool;
+extern crate unicode_normalization;
 
 use std::borrow::ToOwned;
 use std::env;
@@ -45,10 +48,12 @@ macro_rules! command_list {
 "
     cat         Concatenate by row or column
     count       Count records
+    dedup       Remove duplicate rows
     fixlengths  Makes all records have same length
     flatten     Show one field per line
     fmt         Format CSV output (change field delimiter)
     frequency   Show frequency tables
+    groupby     Aggregate columns by group
     headers     Show header names
     help        Show this usage message.
     index       Create CSV index for faster access
@@ -71,7 +76,9 @@ macro_rules! command_list {
 mod cmd;
 mod config;
 mod index;
+mod normalize;
 mod select;
+mod sketch;
 mod util;
 
 static USAGE: &'static str = concat!("
@@ -142,10 +149,12 @@ Please choose one of the following commands:",
 enum Command {
     Cat,
     Count,
+    Dedup,
     FixLengths,
     Flatten,
     Fmt,
     Frequency,
+    GroupBy,
     Headers,
     Help,
     Index,
@@ -177,10 +186,12 @@ impl Command {
         match self {
             Command::Cat => cmd::cat::run(argv),
             Command::Count => cmd::count::run(argv),
+            Command::Dedup => cmd::dedup::run(argv),
             Command::FixLengths => cmd::fixlengths::run(argv),
             Command::Flatten => cmd::flatten::run(argv),
             Command::Fmt => cmd::fmt::run(argv),
             Command::Frequency => cmd::frequency::run(argv),
+            Command::GroupBy => cmd::groupby::run(argv),
             Command::Headers => cmd::headers::run(argv),
             Command::Help => { wout!("{}", USAGE); Ok(()) }
             Command::Index => cmd::index::run(argv),
@@ -0,0 +1,170 @@
+use std::str;
+
+use regex::Regex;
+use unicode_normalization::UnicodeNormalization;
+use unicode_normalization::char::is_combining_mark;
+
+use util::ByteString;
+
+/// KeyNormalizer transforms values before they are compared as keys, e.g.,
+/// by `xsv join` and `xsv dedup`.
+///
+/// A value is first matched against the key regex (when there is one), and
+/// then each of the normalization steps is applied in order.
+#[derive(Clone, Debug, Default)]
+pub struct KeyNormalizer {
+    regex: Option<Regex>,
+    steps: Vec<Step>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Step {
+    Trim,
+    Lower,
+    Nfc,
+    Nfkc,
+    Unaccent,
+    Alnum,
+    Numeric,
+}
+
+impl Step {
+    fn from_name(name: &str) -> Result<Step, String> {
+        Ok(match name {
+            "trim" => Step::Trim,
+            "lower" => Step::Lower,
+            "nfc" => Step::Nfc,
+            "nfkc" => Step::Nfkc,
+            "unaccent" => Step::Unaccent,
+            "alnum" => Step::Alnum,
+            "numeric" => Step::Numeric,
+            _ => return Err(format!(
+                "Unknown normalizer '{}'. Expected one of: trim, lower, nfc, \
+                 nfkc, unaccent, alnum, numeric.", name)),
+        })
+    }
+
+    fn apply(self, s: &str) -> String {
+        match self {
+            Step::Trim => s.trim().to_owned(),
+            Step::Lower => s.chars().flat_map(char::to_lowercase).collect(),
+            Step::Nfc => s.nfc().collect(),
+            Step::Nfkc => s.nfkc().collect(),
+            Step::Unaccent => {
+                s.nfd().filter(|&c| !is_combining_mark(c)).collect()
+            }
+            Step::Alnum => s.chars().filter(|c| c.is_alphanumeric()).collect(),
+            Step::Numeric => canonical_number(s),
+        }
+    }
+}
+
+impl KeyNormalizer {
+    /// Create a normalizer that leaves values unchanged.
+    pub fn new() -> KeyNormalizer {
+        KeyNormalizer::default()
+    }
+
+    /// Trim leading and trailing whitespace.
+    pub fn trim(self, yes: bool) -> KeyNormalizer {
+        if yes { self.step(Step::Trim) } else { self }
+    }
+
+    /// Lowercase values, for case insensitive comparisons.
+    pub fn lowercase(self, yes: bool) -> KeyNormalizer {
+        if yes { self.step(Step::Lower) } else { self }
+    }
+
+    /// Add the normalization steps named in the comma separated `names`.
+    pub fn steps(
+        mut self,
+        names: &Option<String>,
+    ) -> Result<KeyNormalizer, String> {
+        if let Some(ref names) = *names {
+            for name in names.split(',').map(|s| s.trim()) {
+                self = self.step(Step::from_name(name)?);
+            }
+        }
+        Ok(self)
+    }
+
+    /// Only use the part of each value matched by `pattern`. If the regex
+    /// has a capture group, then the first group is used instead of the
+    /// whole match. Values that don't match become empty.
+    pub fn regex(
+        mut self,
+        pattern: &Option<String>,
+    ) -> Result<KeyNormalizer, String> {
+        if let Some(ref pattern) = *pattern {
+            self.regex = Some(Regex::new(pattern).map_err(|e| {
+                format!("Invalid key regex '{}': {}", pattern, e)
+            })?);
+        }
+        Ok(self)
+    }
+
+    /// Returns true if this normalizer leaves every value unchanged.
+    pub fn is_identity(&self) -> bool {
+        self.regex.is_none() && self.steps.is_empty()
+    }
+
+    /// Returns true if this normalizer only trims whitespace, which is how
+    /// the keys of a value index are ordered.
+    pub fn is_trim_only(&self) -> bool {
+        self.regex.is_none() && self.steps == [Step::Trim]
+    }
+
+    pub fn normalize(&self, bs: &[u8]) -> ByteString {
+        if self.is_identity() {
+            return bs.to_vec();
+        }
+        // Values that aren't valid UTF-8 are left alone.
+        let s = match str::from_utf8(bs) {
+            Err(_) => return bs.to_vec(),
+            Ok(s) => s,
+        };
+        let mut s = match self.regex {
+            None => s.to_owned(),
+            Some(ref re) => {
+                match re.captures(s) {
+                    None => return vec![],
+                    Some(caps) => {
+                        caps.get(1)
+                            .or_else(|| caps.get(0))
+                            .map_or("", |m| m.as_str())
+                            .to_owned()
+                    }
+                }
+            }
+        };
+        for step in &self.steps {
+            s = step.apply(&s);
+        }
+        s.into_bytes()
+    }
+
+    fn step(mut self, step: Step) -> KeyNormalizer {
+        self.steps.push(step);
+        self
+    }
+}
+
+/// Write numbers in a canonical form, so that, e.g., `007`, `7` and `7.0`
+/// are all equal. Values that aren't finite numbers (including `inf` and
+/// `NaN`) are returned unchanged.
+fn canonical_number(s: &str) -> String {
+    let t = s.trim();
+    if let Ok(n) = t.parse::<i64>() {
+        return n.to_string();
+    }
+    match t.parse::<f64>() {
+        Ok(n) if n.is_finite() => {
+            if n.fract() == 0.0 && n.abs() < 1e15 {
+                (n as i64).to_string()
+            } else {
+                n.to_string()
+            }
+        }
+        _ => s.to_owned(),
+    }
+}
@@ -7,8 +7,12 @@ use std::slice;
 use std::str::FromStr;
 
 use csv;
+use regex;
+use regex::bytes::Regex;
 use serde::de::{Deserializer, Deserialize, Error};
 
+use cmd::stats::FieldType;
+
 #[derive(Clone)]
 pub struct SelectColumns {
     selectors: Vec<Selector>,
@@ -16,7 +20,7 @@ pub struct SelectColumns {
 }
 
 impl SelectColumns {
-    fn parse(mut s: &str) -> Result<SelectColumns, String> {
+    pub fn parse(mut s: &str) -> Result<SelectColumns, String> {
         let invert =
             if !s.is_empty() && s.as_bytes()[0] == b'!' {
                 s = &s[1..];
@@ -34,6 +38,26 @@ impl SelectColumns {
         &self,
         first_record: &csv::ByteRecord,
         use_names: bool,
+    ) -> Result<Selection, String> {
+        self.typed_selection(first_record, use_names, None)
+    }
+
+    /// Returns true if there are type selectors, which need the types of
+    /// the columns to be resolved.
+    pub fn uses_types(&self) -> bool {
+        self.selectors.iter().any(|sel| match *sel {
+            Selector::Type(_) => true,
+            _ => false,
+        })
+    }
+
+    /// Like `selection`, but with the inferred type of each column for type
+    /// selectors.
+    pub fn typed_selection(
+        &self,
+        first_record: &csv::ByteRecord,
+        use_names: bool,
+        types: Option<&[FieldType]>,
     ) -> Result<Selection, String> {
         if self.selectors.is_empty() {
             return Ok(Selection(if self.invert {
@@ -46,7 +70,7 @@ impl SelectColumns {
 
         let mut map = vec![];
         for sel in &self.selectors {
-            let idxs = sel.indices(first_record, use_names);
+            let idxs = sel.indices(first_record, use_names, types);
             map.extend(idxs?.into_iter());
         }
         if self.invert {
@@ -101,17 +125,17 @@ impl SelectorParser {
             if self.cur().is_none() {
                 break;
             }
-            let f1: OneSelector =
+            let f1: Selector =
                 if self.cur() == Some('-') {
-                    OneSelector::Start
+                    Selector::One(OneSelector::Start)
                 } else {
                     self.parse_one()?
                 };
-            let f2: Option<OneSelector> =
+            let f2: Option<Selector> =
                 if self.cur() == Some('-') {
                     self.bump();
                     Some(if self.is_end_of_selector() {
-                        OneSelector::End
+                        Selector::One(OneSelector::End)
                     } else {
                         self.parse_one()?
                     })
@@ -124,17 +148,31 @@ impl SelectorParser {
                     self.cur().unwrap()));
             }
             sels.push(match f2 {
-                Some(end) => Selector::Range(f1, end),
-                None => Selector::One(f1),
+                Some(f2) => Selector::Range(f1.range_end()?, f2.range_end()?),
+                None => f1,
             });
             self.bump();
         }
         Ok(sels)
     }
 
-    fn parse_one(&mut self) -> Result<OneSelector, String> {
+    /// Parse a single column, or a pattern or type that selects every
+    /// matching column.
+    fn parse_one(&mut self) -> Result<Selector, String> {
+        if self.cur() == Some('/') {
+            self.bump();
+            return self.parse_regex();
+        }
+        if self.cur() == Some(':') {
+            self.bump();
+            return self.parse_type();
+        }
+        if self.is_glob() {
+            return Ok(self.parse_glob());
+        }
+        let quoted = self.cur() == Some('"');
         let name =
-            if self.cur() == Some('"') {
+            if quoted {
                 self.bump();
                 self.parse_quoted_name()?
             } else {
@@ -142,15 +180,41 @@ impl SelectorParser {
             };
         Ok(if self.cur() == Some('[') {
             let idx = self.parse_index()?;
-            OneSelector::IndexedName(name, idx)
+            Selector::One(OneSelector::IndexedName(name, idx))
         } else {
-            match FromStr::from_str(&name) {
+            Selector::One(match FromStr::from_str(&name) {
                 Err(_) => OneSelector::IndexedName(name, 0),
                 Ok(idx) => OneSelector::Index(idx),
-            }
+            })
         })
     }
 
+    /// Returns true if the rest of the selector is an unquoted glob. It is
+    /// checked before ranges are split, so that a glob can contain `-`.
+    fn is_glob(&self) -> bool {
+        self.cur() != Some('"')
+            && self.chars[self.pos..].iter()
+                                     .take_while(|&&c| c != ',')
+                                     .any(|&c| c == '*')
+    }
+
+    /// Parse a glob, where `*` matches any sequence of characters.
+    fn parse_glob(&mut self) -> Selector {
+        let mut glob = String::new();
+        while !self.is_end_of_selector() {
+            glob.push(self.cur().unwrap());
+            self.bump();
+        }
+        let parts: Vec<String> = glob.split('*').map(regex::escape).collect();
+        let re = Regex::new(&format!("(?s)^{}$", parts.join(".*"))).unwrap();
+        Selector::Pattern(glob, re)
+    }
+
+    fn parse_type(&mut self) -> Result<Selector, String> {
+        let name = self.parse_name()?;
+        ColumnType::from_name(&name).map(Selector::Type)
+    }
+
     fn parse_name(&mut self) -> Result<String, String> {
         let mut name = String::new();
         loop {
@@ -186,6 +250,31 @@ impl SelectorParser {
         Ok(name)
     }
 
+    fn parse_regex(&mut self) -> Result<Selector, String> {
+        let mut re = String::new();
+        loop {
+            match self.cur() {
+                None => {
+                    return Err("Unclosed regex, missing closing /."
+                               .to_owned());
+                }
+                Some('/') => { self.bump(); break; }
+                Some('\\') if self.peek() == Some('/') => {
+                    self.bump();
+                    self.bump();
+                    re.push('/');
+                }
+                Some(c) => { re.push(c); self.bump(); }
+            }
+        }
+        match Regex::new(&re) {
+            Ok(compiled) => {
+                Ok(Selector::Pattern(format!("/{}/", re), compiled))
+            }
+            Err(err) => Err(format!("Invalid regex '{}': {}", re, err)),
+        }
+    }
+
     fn parse_index(&mut self) -> Result<usize, String> {
         assert_eq!(self.cur().unwrap(), '[');
         self.bump();
@@ -210,6 +299,10 @@ impl SelectorParser {
         self.chars.get(self.pos).cloned()
     }
 
+    fn peek(&self) -> Option<char> {
+        self.chars.get(self.pos + 1).cloned()
+    }
+
     fn is_end_of_field(&self) -> bool {
         self.cur().map_or(true, |c| c == ',' || c == '-')
     }
@@ -227,6 +320,63 @@ impl SelectorParser {
 enum Selector {
     One(OneSelector),
     Range(OneSelector, OneSelector),
+    /// A regex or a glob, which selects every column whose name matches.
+    /// The string is the pattern as given, for messages.
+    Pattern(String, Regex),
+    /// A type, which selects every column of that type.
+    Type(ColumnType),
+}
+
+/// The column types that type selectors, like `:numeric`, match. They are
+/// the types that `xsv stats` infers.
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum ColumnType {
+    Numeric,
+    Integer,
+    Float,
+    Date,
+    Boolean,
+    String,
+}
+
+impl ColumnType {
+    fn from_name(name: &str) -> Result<ColumnType, String> {
+        Ok(match name {
+            "numeric" => ColumnType::Numeric,
+            "integer" => ColumnType::Integer,
+            "float" => ColumnType::Float,
+            "date" => ColumnType::Date,
+            "boolean" => ColumnType::Boolean,
+            "string" => ColumnType::String,
+            _ => return Err(format!(
+                "Unknown column type ':{}'. Expected one of: numeric, \
+                 integer, float, date, boolean, string.", name)),
+        })
+    }
+
+    fn name(self) -> &'static str {
+        match self {
+            ColumnType::Numeric => "numeric",
+            ColumnType::Integer => "integer",
+            ColumnType::Float => "float",
+            ColumnType::Date => "date",
+            ColumnType::Boolean => "boolean",
+            ColumnType::String => "string",
+        }
+    }
+
+    fn matches(self, typ: FieldType) -> bool {
+        match self {
+            ColumnType::Numeric => typ.is_number(),
+            ColumnType::Integer => typ == FieldType::TInteger,
+            ColumnType::Float => typ == FieldType::TFloat,
+            ColumnType::Date => {
+                typ == FieldType::TDate || typ == FieldType::TDateTime
+            }
+            ColumnType::Boolean => typ == FieldType::TBoolean,
+            ColumnType::String => typ == FieldType::TUnicode,
+        }
+    }
 }
 
 #[derive(Clone)]
@@ -238,12 +388,64 @@ enum OneSelector {
 }
 
 impl Selector {
+    /// Returns the column at the start or end of a range. A pattern can
+    /// match any number of columns, so it can't be used in a range.
+    fn range_end(self) -> Result<OneSelector, String> {
+        match self {
+            Selector::One(sel) => Ok(sel),
+            Selector::Range(..) => Err("Cannot nest ranges.".to_owned()),
+            Selector::Pattern(pat, _) => Err(format!(
+                "Cannot use a regex or glob ('{}') in a range.", pat)),
+            Selector::Type(typ) => Err(format!(
+                "Cannot use a type (':{}') in a range.", typ.name())),
+        }
+    }
+
     fn indices(
         &self,
         first_record: &csv::ByteRecord,
         use_names: bool,
+        types: Option<&[FieldType]>,
     ) -> Result<Vec<usize>, String> {
         match *self {
+            Selector::Type(typ) => {
+                let types = match types {
+                    None => return Err(format!(
+                        "Cannot use a type (':{}') in this selection.",
+                        typ.name())),
+                    Some(types) => types,
+                };
+                let inds: Vec<usize> =
+                    (0..first_record.len())
+                        .filter(|&i| {
+                            types.get(i).map_or(false, |&t| typ.matches(t))
+                        })
+                        .collect();
+                if inds.is_empty() {
+                    return Err(format!("Selector ':{}' does not match any \
+                                        column in the given CSV data.",
+                                       typ.name()));
+                }
+                Ok(inds)
+            }
+            Selector::Pattern(ref pat, ref re) => {
+                if !use_names {
+                    return Err(format!("Cannot use names ('{}') in selection \
+                                        with --no-headers set.", pat));
+                }
+                let inds: Vec<usize> =
+                    first_record.iter()
+                                .enumerate()
+                                .filter(|&(_, field)| re.is_match(field))
+                                .map(|(i, _)| i)
+                                .collect();
+                if inds.is_empty() {
+                    return Err(format!("Selector '{}' does not match any \
+                                        header in the given CSV data.",
+                                       pat));
+                }
+                Ok(inds)
+            }
             Selector::One(ref sel) => {
                 sel.index(first_record, use_names).map(|i| vec![i])
             }
@@ -327,6 +529,8 @@ impl fmt::Debug for Selector {
             Selector::One(ref sel) => sel.fmt(f),
             Selector::Range(ref s, ref e) =>
                 write!(f, "Range({:?}, {:?})", s, e),
+            Selector::Pattern(ref s, _) => write!(f, "Pattern({})", s),
+            Selector::Type(typ) => write!(f, "Type({})", typ.name()),
         }
     }
 }
@@ -0,0 +1,368 @@
+use std::cmp::Ordering;
+use std::collections::{BTreeSet, HashMap};
+use std::collections::hash_map::DefaultHasher;
+use std::f64::consts::PI;
+use std::hash::Hasher;
+
+use stats::Commute;
+
+use util::ByteString;
+
+/// TDigest estimates quantiles of a stream of numbers in constant memory.
+///
+/// This is the merging variant of the t-digest described by Dunning and
+/// Ertl. Numbers are buffered and periodically merged into a sorted list of
+/// centroids, which are kept small near the tails of the distribution so
+/// that extreme quantiles stay accurate. Two digests can be merged, which
+/// makes it possible to build them in parallel.
+#[derive(Clone, Debug)]
+pub struct TDigest {
+    compression: f64,
+    centroids: Vec<Centroid>,
+    buffer: Vec<Centroid>,
+    count: f64,
+    min: f64,
+    max: f64,
+}
+
+#[derive(Clone, Copy, Debug)]
+struct Centroid {
+    mean: f64,
+    weight: f64,
+}
+
+impl TDigest {
+    /// Create a digest with the given compression. Larger values are more
+    /// accurate and use more memory. The number of centroids is bounded by
+    /// a small multiple of the compression.
+    pub fn new(compression: f64) -> TDigest {
+        TDigest {
+            compression: compression,
+            centroids: vec![],
+            buffer: vec![],
+            count: 0.0,
+            min: f64::INFINITY,
+            max: f64::NEG_INFINITY,
+        }
+    }
+
+    pub fn add(&mut self, sample: f64) {
+        self.buffer.push(Centroid { mean: sample, weight: 1.0 });
+        self.count += 1.0;
+        if sample < self.min {
+            self.min = sample;
+        }
+        if sample > self.max {
+            self.max = sample;
+        }
+        if self.buffer.len() >= 5 * self.compression as usize {
+            self.compress();
+        }
+    }
+
+    /// Estimate the `q`th quantile, where `q` is between `0` and `1`.
+    ///
+    /// Returns `None` if no numbers have been added.
+    pub fn quantile(&mut self, q: f64) -> Option<f64> {
+        self.compress();
+        if self.centroids.is_empty() {
+            return None;
+        }
+        if q <= 0.0 {
+            return Some(self.min);
+        }
+        if q >= 1.0 {
+            return Some(self.max);
+        }
+
+        // Each centroid is assumed to sit in the middle of the weight it
+        // covers, and quantiles between centroids are interpolated.
+        let target = q * self.count;
+        let (mut cum, mut prev) = (0.0, (self.min, 0.0));
+        for c in &self.centroids {
+            let mid = cum + c.weight / 2.0;
+            if target < mid {
+                return Some(interpolate(prev, (c.mean, mid), target));
+            }
+            prev = (c.mean, mid);
+            cum += c.weight;
+        }
+        Some(interpolate(prev, (self.max, self.count), target))
+    }
+
+    /// Merge the buffered numbers into the centroids.
+    fn compress(&mut self) {
+        if self.buffer.is_empty() {
+            return;
+        }
+        let mut all: Vec<Centroid> = self.buffer.drain(..).collect();
+        all.append(&mut self.centroids);
+        all.sort_by(|c1, c2| {
+            c1.mean.partial_cmp(&c2.mean).unwrap_or(Ordering::Equal)
+        });
+
+        let total = self.count;
+        let mut done = 0.0;
+        let mut limit = total * self.k_inv(self.k(0.0) + 1.0);
+        let mut cur = all[0];
+        for &c in &all[1..] {
+            if done + cur.weight + c.weight <= limit {
+                let weight = cur.weight + c.weight;
+                cur.mean += (c.mean - cur.mean) * c.weight / weight;
+                cur.weight = weight;
+            } else {
+                done += cur.weight;
+                self.centroids.push(cur);
+                limit = total * self.k_inv(self.k(done / total) + 1.0);
+                cur = c;
+            }
+        }
+        self.centroids.push(cur);
+    }
+
+    /// The scale function, which maps a quantile to the index of the
+    /// centroid that covers it.
+    fn k(&self, q: f64) -> f64 {
+        self.compression / (2.0 * PI) * (2.0 * q - 1.0).asin()
+    }
+
+    fn k_inv(&self, k: f64) -> f64 {
+        let k = k.min(self.compression / 4.0);
+        ((2.0 * PI * k / self.compression).sin() + 1.0) / 2.0
+    }
+}
+
+impl Default for TDigest {
+    fn default() -> TDigest {
+        TDigest::new(100.0)
+    }
+}
+
+impl Commute for TDigest {
+    fn merge(&mut self, mut other: TDigest) {
+        self.buffer.append(&mut other.centroids);
+        self.buffer.append(&mut other.buffer);
+        self.count += other.count;
+        self.min = self.min.min(other.min);
+        self.max = self.max.max(other.max);
+        self.compress();
+    }
+}
+
+/// Linearly interpolate the value at `x` between two `(value, x)` points.
+fn interpolate(from: (f64, f64), to: (f64, f64), x: f64) -> f64 {
+    if to.1 <= from.1 {
+        return to.0;
+    }
+    from.0 + (to.0 - from.0) * (x - from.1) / (to.1 - from.1)
+}
+
+/// HyperLogLog estimates the number of distinct values in a stream in
+/// constant memory.
+///
+/// It uses `2^precision` one byte registers. The standard error of the
+/// estimate is about `1.04 / sqrt(2^precision)`, e.g., 0.8% for the default
+/// precision of 14, which uses 16KB. Two sketches with the same precision
+/// can be merged.
+#[derive(Clone, Debug)]
+pub struct HyperLogLog {
+    precision: u8,
+    registers: Vec<u8>,
+}
+
+impl HyperLogLog {
+    pub const MIN_PRECISION: u8 = 4;
+    pub const MAX_PRECISION: u8 = 18;
+
+    /// Create an empty sketch. Panics if the precision is not between
+    /// `MIN_PRECISION` and `MAX_PRECISION`; use `check_precision` first to
+    /// validate user input.
+    pub fn new(precision: u8) -> HyperLogLog {
+        assert!(HyperLogLog::check_precision(precision as usize).is_ok());
+        HyperLogLog {
+            precision: precision,
+            registers: vec![0; 1 << precision],
+        }
+    }
+
+    pub fn check_precision(precision: usize) -> Result<u8, String> {
+        let (min, max) =
+            (HyperLogLog::MIN_PRECISION, HyperLogLog::MAX_PRECISION);
+        if precision < min as usize || precision > max as usize {
+            return Err(format!(
+                "The precision must be between {} and {}, but it is {}.",
+                min, max, precision));
+        }
+        Ok(precision as u8)
+    }
+
+    pub fn add(&mut self, sample: &[u8]) {
+        let mut hasher = DefaultHasher::new();
+        hasher.write(sample);
+        let hash = hasher.finish();
+
+        // The first bits of the hash pick a register, which remembers the
+        // longest run of leading zeros seen in the rest of the bits.
+        let p = self.precision as u32;
+        let i = (hash >> (64 - p)) as usize;
+        let rest = (hash << p) | (1 << (p - 1));
+        let zeros = rest.leading_zeros() as u8 + 1;
+        if zeros > self.registers[i] {
+            self.registers[i] = zeros;
+        }
+    }
+
+    /// Returns the estimated number of distinct values added.
+    pub fn count(&self) -> u64 {
+        let m = self.registers.len() as f64;
+        let alpha = match self.registers.len() {
+            16 => 0.673,
+            32 => 0.697,
+            64 => 0.709,
+            _ => 0.7213 / (1.0 + 1.079 / m),
+        };
+        let (mut sum, mut empty) = (0.0, 0);
+        for &r in &self.registers {
+            sum += 1.0 / (1u64 << r) as f64;
+            if r == 0 {
+                empty += 1;
+            }
+        }
+        let estimate = alpha * m * m / sum;
+        // Small cardinalities are estimated better by counting the empty
+        // registers (linear counting).
+        if estimate <= 2.5 * m && empty > 0 {
+            (m * (m / empty as f64).ln()).round() as u64
+        } else {
+            estimate.round() as u64
+        }
+    }
+}
+
+impl Commute for HyperLogLog {
+    fn merge(&mut self, other: HyperLogLog) {
+        assert_eq!(self.precision, other.precision);
+        for (r1, r2) in self.registers.iter_mut().zip(other.registers) {
+            if r2 > *r1 {
+                *r1 = r2;
+            }
+        }
+    }
+}
+
+/// SpaceSaving finds the most frequent values of a stream in memory bounded
+/// by its capacity (Metwally et al., "Efficient Computation of Frequent and
+/// Top-k Elements in Data Streams", 2005).
+///
+/// At most `capacity` values are tracked. When a value that isn't tracked
+/// comes in and all counters are taken, it replaces the value with the
+/// smallest count, and inherits that count as its error. Every value that
+/// occurs more than `n / capacity` times in a stream of `n` values is
+/// tracked, and each count overestimates the true count by at most its
+/// error. Two summaries with the same capacity can be merged (Agarwal et
+/// al., "Mergeable Summaries", 2012).
+#[derive(Clone, Debug)]
+pub struct SpaceSaving {
+    capacity: usize,
+    counters: HashMap<ByteString, Counter>,
+    /// The tracked values ordered by count, to find the smallest quickly.
+    order: BTreeSet<(u64, ByteString)>,
+}
+
+#[derive(Clone, Copy, Debug)]
+struct Counter {
+    count: u64,
+    error: u64,
+}
+
+impl SpaceSaving {
+    pub fn new(capacity: usize) -> SpaceSaving {
+        assert!(capacity > 0);
+        SpaceSaving {
+            capacity: capacity,
+            counters: HashMap::new(),
+            order: BTreeSet::new(),
+        }
+    }
+
+    pub fn add(&mut self, value: ByteString) {
+        if let Some(c) = self.counters.get_mut(&value) {
+            let key = (c.count, value);
+            self.order.remove(&key);
+            c.count += 1;
+            self.order.insert((c.count, key.1));
+            return;
+        }
+        let (count, error) =
+            if self.counters.len() < self.capacity {
+                (1, 0)
+            } else {
+                let smallest = self.order.iter().next().unwrap().clone();
+                self.order.remove(&smallest);
+                self.counters.remove(&smallest.1);
+                (smallest.0 + 1, smallest.0)
+            };
+        self.counters.insert(value.clone(), Counter {
+            count: count,
+            error: error,
+        });
+        self.order.insert((count, value));
+    }
+
+    /// Returns the tracked values with their estimated counts and errors,
+    /// from the most frequent to the least frequent.
+    pub fn top(&self) -> Vec<(&ByteString, u64, u64)> {
+        self.order.iter().rev().map(|&(count, ref value)| {
+            (value, count, self.counters[value].error)
+        }).collect()
+    }
+
+    /// The count that a value that isn't tracked could have.
+    fn min_count(&self) -> u64 {
+        if self.counters.len() < self.capacity {
+            0
+        } else {
+            self.order.iter().next().map_or(0, |&(count, _)| count)
+        }
+    }
+}
+
+impl Commute for SpaceSaving {
+    fn merge(&mut self, mut other: SpaceSaving) {
+        assert_eq!(self.capacity, other.capacity);
+        // A value that is missing from one summary may have occurred up to
+        // its smallest count in its stream.
+        let (min1, min2) = (self.min_count(), other.min_count());
+        let mut all = vec![];
+        for (value, c) in self.counters.drain() {
+            let c = match other.counters.remove(&value) {
+                Some(o) => Counter {
+                    count: c.count + o.count,
+                    error: c.error + o.error,
+                },
+                None => Counter {
+                    count: c.count + min2,
+                    error: c.error + min2,
+                },
+            };
+            all.push((value, c));
+        }
+        for (value, o) in other.counters {
+            all.push((value, Counter {
+                count: o.count + min1,
+                error: o.error + min1,
+            }));
+        }
+
+        // The values come out of hash maps in no particular order, so break
+        // ties by value to keep the same ones from run to run.
+        all.sort_by(|&(ref v1, c1), &(ref v2, c2)| {
+            c2.count.cmp(&c1.count).then_with(|| v1.cmp(v2))
+        });
+        all.truncate(self.capacity);
+        self.order = all.iter()
+                        .map(|&(ref v, c)| (c.count, v.clone()))
+                        .collect();
+        self.counters = all.into_iter().collect();
+    }
+}
@@ -13,6 +13,9 @@ use serde::de::{Deserializer, Deserialize, DeserializeOwned, Error};
 
 use CliResult;
 use config::{Config, Delimiter};
+use select::Selection;
+
+pub type ByteString = Vec<u8>;
 
 pub fn num_cpus() -> usize {
     num_cpus::get()
@@ -121,6 +124,15 @@ pub fn idx_path(csv_path: &Path) -> PathBuf {
 
 pub type Idx = Option<usize>;
 
+/// The path of the value index of the columns in `sel` of the CSV data at
+/// `csv_path`, e.g., `data.csv.1-3.vidx` for its first and third columns.
+pub fn vidx_path(csv_path: &Path, sel: &Selection) -> PathBuf {
+    let cols: Vec<String> = sel.iter().map(|i| (i + 1).to_string()).collect();
+    let mut p = csv_path.to_path_buf().into_os_string().into_string().unwrap();
+    p.push_str(&format!(".{}.vidx", cols.join("-")));
+    PathBuf::from(&p)
+}
+
 pub fn range(start: Idx, end: Idx, len: Idx, index: Idx)
             -> Result<(usize, usize), String> {
     match (start, end, len, index) {
@@ -0,0 +1,174 @@
+use workdir::Workdir;
+
+fn setup(name: &str) -> Workdir {
+    let wrk = Workdir::new(name);
+    wrk.create("in.csv", vec![
+        svec!["id", "name"],
+        svec!["1", "alice"],
+        svec!["2", "bob"],
+        svec!["1", "Alice "],
+        svec!["3", "carol"],
+        svec!["2", "BOB"],
+        svec!["2", "bob"],
+    ]);
+    wrk
+}
+
+#[test]
+fn dedup_all_columns() {
+    let wrk = setup("dedup_all_columns");
+    let mut cmd = wrk.command("dedup");
+    cmd.arg("in.csv");
+
+    let got: Vec<Vec<String>> = wrk.read_stdout(&mut cmd);
+    let expected = vec![
+        svec!["id", "name"],
+        svec!["1", "alice"],
+        svec!["2", "bob"],
+        svec!["1", "Alice "],
+        svec!["3", "carol"],
+        svec!["2", "BOB"],
+    ];
+    assert_eq!(got, expected);
+}
+
+#[test]
+fn dedup_select_keep_first() {
+    let wrk = setup("dedup_select_keep_first");
+    let mut cmd = wrk.command("dedup");
+    cmd.args(&["-s", "id"]).arg("in.csv");
+
+    let got: Vec<Vec<String>> = wrk.read_stdout(&mut cmd);
+    let expected = vec![
+        svec!["id", "name"],
+        svec!["1", "alice"],
+        svec!["2", "bob"],
+        svec!["3", "carol"],
+    ];
+    assert_eq!(got, expected);
+}
+
+#[test]
+fn dedup_keep_last() {
+    let wrk = setup("dedup_keep_last");
+    let mut cmd = wrk.command("dedup");
+    cmd.args(&["-s", "id", "--keep-last"]).arg("in.csv");
+
+    let got: Vec<Vec<String>> = wrk.read_stdout(&mut cmd);
+    let expected = vec![
+        svec!["id", "name"],
+        svec!["1", "Alice "],
+        svec!["3", "carol"],
+        svec!["2", "bob"],
+    ];
+    assert_eq!(got, expected);
+}
+
+#[test]
+fn dedup_drop_all() {
+    let wrk = setup("dedup_drop_all");
+    let mut cmd = wrk.command("dedup");
+    cmd.args(&["-s", "id", "--drop-all"]).arg("in.csv");
+
+    let got: Vec<Vec<String>> = wrk.read_stdout(&mut cmd);
+    let expected = vec![
+        svec!["id", "name"],
+        svec!["3", "carol"],
+    ];
+    assert_eq!(got, expected);
+}
+
+#[test]
+fn dedup_trim_no_case() {
+    let wrk = setup("dedup_trim_no_case");
+    let mut cmd = wrk.command("dedup");
+    cmd.args(&["-s", "name", "--trim", "--no-case"]).arg("in.csv");
+
+    let got: Vec<Vec<String>> = wrk.read_stdout(&mut cmd);
+    let expected = vec![
+        svec!["id", "name"],
+        svec!["1", "alice"],
+        svec!["2", "bob"],
+        svec!["3", "carol"],
+    ];
+    assert_eq!(got, expected);
+}
+
+#[test]
+fn dedup_dupes_output() {
+    let wrk = setup("dedup_dupes_output");
+    let mut cmd = wrk.command("dedup");
+    cmd.args(&["-s", "id", "--dupes-output", "dupes.csv"]).arg("in.csv");
+    wrk.run(&mut cmd);
+
+    let got: String = wrk.from_str(&wrk.path("dupes.csv"));
+    assert_eq!(got, "id,name\n1,Alice \n2,BOB\n2,bob\n");
+}
+
+#[test]
+fn dedup_no_headers() {
+    let wrk = setup("dedup_no_headers");
+    let mut cmd = wrk.command("dedup");
+    cmd.args(&["-s", "1", "--no-headers"]).arg("in.csv");
+
+    let got: Vec<Vec<String>> = wrk.read_stdout(&mut cmd);
+    let expected = vec![
+        svec!["id", "name"],
+        svec!["1", "alice"],
+        svec!["2", "bob"],
+        svec!["3", "carol"],
+    ];
+    assert_eq!(got, expected);
+}
+
+#[test]
+fn dedup_conflicting_policies() {
+    let wrk = setup("dedup_conflicting_policies");
+    let mut cmd = wrk.command("dedup");
+    cmd.args(&["--keep-last", "--drop-all"]).arg("in.csv");
+    wrk.assert_err(&mut cmd);
+}
+
+#[test]
+fn dedup_normalize() {
+    let wrk = Workdir::new("dedup_normalize");
+    wrk.create("in.csv", vec![
+        svec!["name"],
+        svec!["Amélie"],
+        svec!["amelie"],
+        svec!["Bob"],
+    ]);
+    let mut cmd = wrk.command("dedup");
+    cmd.args(&["--normalize", "unaccent,lower"]).arg("in.csv");
+
+    let got: Vec<Vec<String>> = wrk.read_stdout(&mut cmd);
+    let expected = vec![svec!["name"], svec!["Amélie"], svec!["Bob"]];
+    assert_eq!(got, expected);
+}
+
+#[test]
+fn dedup_normalize_numeric_not_finite() {
+    let wrk = Workdir::new("dedup_normalize_numeric_not_finite");
+    wrk.create("in.csv", vec![
+        svec!["n"],
+        svec!["inf"],
+        svec!["Infinity"],
+        svec!["NaN"],
+        svec!["nan"],
+        svec!["1e0"],
+        svec!["1"],
+    ]);
+    let mut cmd = wrk.command("dedup");
+    cmd.args(&["--normalize", "numeric"]).arg("in.csv");
+
+    let got: Vec<Vec<String>> = wrk.read_stdout(&mut cmd);
+    let expected = vec![
+        svec!["n"],
+        svec!["inf"],
+        svec!["Infinity"],
+        svec!["NaN"],
+        svec!["nan"],
+        svec!["1e0"],
+    ];
+    assert_eq!(got, expected);
+}
@@ -88,7 +88,9 @@ fn frequency_limit() {
     got.sort();
     let expected = vec![
         svec!["field", "value", "count"],
+        svec!["h1", "(other)", "3"],
         svec!["h1", "a", "3"],
+        svec!["h2", "(other)", "3"],
         svec!["h2", "z", "3"],
     ];
     assert_eq!(got, expected);
@@ -103,6 +105,7 @@ fn frequency_asc() {
     got.sort();
     let expected = vec![
         svec!["field", "value", "count"],
+        svec!["h2", "(other)", "5"],
         svec!["h2", "x", "1"],
     ];
     assert_eq!(got, expected);
@@ -124,6 +127,387 @@ fn frequency_select() {
     assert_eq!(got, expected);
 }
 
+#[test]
+fn frequency_percent() {
+    let (wrk, mut cmd) = setup("frequency_percent");
+    cmd.args(&["--percent", "--select", "h2"]);
+
+    let got: Vec<Vec<String>> = wrk.read_stdout(&mut cmd);
+    let expected = vec![
+        svec!["field", "value", "count", "percent", "cumulative_percent"],
+        svec!["h2", "z", "3", "50.00", "50.00"],
+        svec!["h2", "y", "2", "33.33", "83.33"],
+        svec!["h2", "x", "1", "16.67", "100.00"],
+    ];
+    assert_eq!(got, expected);
+}
+
+#[test]
+fn frequency_percent_non_null() {
+    let (wrk, mut cmd) = setup("frequency_percent_non_null");
+    cmd.args(&["--percent", "--percent-of", "non-null", "--select", "h1"]);
+
+    let got: Vec<Vec<String>> = wrk.read_stdout(&mut cmd);
+    assert_eq!(got[1], svec!["h1", "a", "3", "60.00", "60.00"]);
+    // The NULL row has no percentages, unlike the '(NULL)' string.
+    assert!(got.contains(&svec!["h1", "(NULL)", "1", "", ""]));
+    let total: f64 = got[1..].iter()
+        .filter(|row| !row[3].is_empty())
+        .map(|row| row[3].parse::<f64>().unwrap())
+        .sum();
+    assert_eq!(total.round(), 100.0);
+}
+
+#[test]
+fn frequency_other() {
+    let (wrk, mut cmd) = setup("frequency_other");
+    cmd.args(&["--limit", "1"]);
+
+    let got: Vec<Vec<String>> = wrk.read_stdout(&mut cmd);
+    let expected = vec![
+        svec!["field", "value", "count"],
+        svec!["h1", "a", "3"],
+        svec!["h1", "(other)", "3"],
+        svec!["h2", "z", "3"],
+        svec!["h2", "(other)", "3"],
+    ];
+    assert_eq!(got, expected);
+}
+
+#[test]
+fn frequency_no_other() {
+    let (wrk, mut cmd) = setup("frequency_no_other");
+    cmd.args(&["--no-other", "--limit", "1"]);
+
+    let got: Vec<Vec<String>> = wrk.read_stdout(&mut cmd);
+    let expected = vec![
+        svec!["field", "value", "count"],
+        svec!["h1", "a", "3"],
+        svec!["h2", "z", "3"],
+    ];
+    assert_eq!(got, expected);
+}
+
+#[test]
+fn frequency_other_not_truncated() {
+    let (wrk, mut cmd) = setup("frequency_other_not_truncated");
+    cmd.args(&["--limit", "3", "--select", "h2"]);
+
+    let got: Vec<Vec<String>> = wrk.read_stdout(&mut cmd);
+    assert_eq!(got.len(), 4);
+    assert!(got.iter().all(|row| row[1] != "(other)"));
+}
+
+#[test]
+fn frequency_joint_other_percent() {
+    let (wrk, mut cmd) = setup("frequency_joint_other_percent");
+    cmd.args(&["--joint", "--percent", "--limit", "1"]);
+
+    let got: Vec<Vec<String>> = wrk.read_stdout(&mut cmd);
+    let expected = vec![
+        svec!["h1", "h2", "count", "percent", "cumulative_percent"],
+        svec!["a", "y", "2", "33.33", "33.33"],
+        svec!["(other)", "(other)", "4", "66.67", "100.00"],
+    ];
+    assert_eq!(got, expected);
+}
+
+#[test]
+fn frequency_joint() {
+    let (wrk, mut cmd) = setup("frequency_joint");
+    cmd.arg("--joint");
+
+    let mut got: Vec<Vec<String>> = wrk.read_stdout(&mut cmd);
+    assert_eq!(got.remove(0), svec!["h1", "h2", "count"]);
+    assert_eq!(got[0], svec!["a", "y", "2"]);
+    got.sort();
+    let expected = vec![
+        svec!["(NULL)", "x", "1"],
+        svec!["(NULL)", "z", "1"],
+        svec!["a", "y", "2"],
+        svec!["a", "z", "1"],
+        svec!["b", "z", "1"],
+    ];
+    assert_eq!(got, expected);
+}
+
+#[test]
+fn frequency_joint_limit_asc_no_nulls() {
+    let (wrk, mut cmd) = setup("frequency_joint_limit_asc_no_nulls");
+    cmd.args(&["--joint", "--asc", "--no-nulls", "--limit", "3"])
+       .args(&["--select", "h2,h1"]);
+
+    let mut got: Vec<Vec<String>> = wrk.read_stdout(&mut cmd);
+    assert_eq!(got.remove(0), svec!["h2", "h1", "count"]);
+    got.sort();
+    let expected = vec![
+        svec!["(other)", "(other)", "2"],
+        svec!["x", "(NULL)", "1"],
+        svec!["z", "a", "1"],
+        svec!["z", "b", "1"],
+    ];
+    assert_eq!(got, expected);
+}
+
+#[test]
+fn frequency_joint_index() {
+    let wrk = Workdir::new("frequency_joint_index");
+    let mut rows = vec![svec!["country", "device"]];
+    for i in 0..100 {
+        let country = if i % 2 == 0 { "fr" } else { "de" };
+        let device = if i % 5 == 0 { "phone" } else { "desktop" };
+        rows.push(svec![country, device]);
+    }
+    wrk.create_indexed("in.csv", rows);
+    let mut cmd = wrk.command("frequency");
+    cmd.args(&["--joint", "--jobs", "4"]).arg("in.csv");
+
+    let got: Vec<Vec<String>> = wrk.read_stdout(&mut cmd);
+    let expected = vec![
+        svec!["country", "device", "count"],
+        svec!["de", "desktop", "40"],
+        svec!["fr", "desktop", "40"],
+        svec!["fr", "phone", "10"],
+        svec!["de", "phone", "10"],
+    ];
+    assert_eq!(got[0], expected[0]);
+    let mut got = got[1..].to_vec();
+    let mut expected = expected[1..].to_vec();
+    got.sort();
+    expected.sort();
+    assert_eq!(got, expected);
+}
+
+#[test]
+fn frequency_approx_cardinality() {
+    let (wrk, mut cmd) = setup("frequency_approx_cardinality");
+    cmd.arg("--approx-cardinality");
+
+    let got: Vec<Vec<String>> = wrk.read_stdout(&mut cmd);
+    let expected = vec![
+        svec!["field", "cardinality"],
+        svec!["h1", "4"],
+        svec!["h2", "3"],
+    ];
+    assert_eq!(got, expected);
+}
+
+#[test]
+fn frequency_approx_cardinality_no_nulls() {
+    let (wrk, mut cmd) = setup("frequency_approx_cardinality_no_nulls");
+    cmd.args(&["--approx-cardinality", "--no-nulls"]);
+
+    let got: Vec<Vec<String>> = wrk.read_stdout(&mut cmd);
+    let expected = vec![
+        svec!["field", "cardinality"],
+        svec!["h1", "3"],
+        svec!["h2", "3"],
+    ];
+    assert_eq!(got, expected);
+}
+
+fn setup_numbers(name: &str) -> (Workdir, process::Command) {
+    let rows = vec![
+        svec!["n"],
+        svec!["1"],
+        svec!["5"],
+        svec!["12"],
+        svec!["15.5"],
+        svec!["19"],
+        svec!["-2"],
+        svec!["n/a"],
+    ];
+
+    let wrk = Workdir::new(name);
+    wrk.create("in.csv", rows);
+
+    let mut cmd = wrk.command("frequency");
+    cmd.arg("in.csv").args(&["--limit", "0"]);
+
+    (wrk, cmd)
+}
+
+#[test]
+fn frequency_bin_width() {
+    let (wrk, mut cmd) = setup_numbers("frequency_bin_width");
+    cmd.args(&["--bin-width", "10"]);
+
+    let mut got: Vec<Vec<String>> = wrk.read_stdout(&mut cmd);
+    got.sort();
+    let expected = vec![
+        svec!["field", "value", "count"],
+        svec!["n", "[-10, 0)", "1"],
+        svec!["n", "[0, 10)", "2"],
+        svec!["n", "[10, 20)", "3"],
+        svec!["n", "n/a", "1"],
+    ];
+    assert_eq!(got, expected);
+}
+
+#[test]
+fn frequency_bin_width_on_edges() {
+    let wrk = Workdir::new("frequency_bin_width_on_edges");
+    let rows = vec![
+        svec!["n"],
+        svec!["0.3"],
+        svec!["0.7"],
+        svec!["0.29"],
+    ];
+    wrk.create("in.csv", rows);
+    let mut cmd = wrk.command("frequency");
+    cmd.arg("in.csv").args(&["--limit", "0", "--bin-width", "0.1"]);
+
+    let mut got: Vec<Vec<String>> = wrk.read_stdout(&mut cmd);
+    got.sort();
+    let expected = vec![
+        svec!["field", "value", "count"],
+        svec!["n", "[0.2, 0.3)", "1"],
+        svec!["n", "[0.3, 0.4)", "1"],
+        svec!["n", "[0.7, 0.8)", "1"],
+    ];
+    assert_eq!(got, expected);
+}
+
+#[test]
+fn frequency_bin_width_large_values() {
+    let wrk = Workdir::new("frequency_bin_width_large_values");
+    let rows = vec![
+        svec!["n"],
+        svec!["1000000019.5"],
+        svec!["1000000020"],
+    ];
+    wrk.create("in.csv", rows);
+    let mut cmd = wrk.command("frequency");
+    cmd.arg("in.csv").args(&["--limit", "0", "--bin-width", "60"]);
+
+    let mut got: Vec<Vec<String>> = wrk.read_stdout(&mut cmd);
+    got.sort();
+    let expected = vec![
+        svec!["field", "value", "count"],
+        svec!["n", "[1000000020, 1000000080)", "1"],
+        svec!["n", "[999999960, 1000000020)", "1"],
+    ];
+    assert_eq!(got, expected);
+}
+
+#[test]
+fn frequency_bin_width_below_edges() {
+    let wrk = Workdir::new("frequency_bin_width_below_edges");
+    let rows = vec![
+        svec!["n"],
+        svec!["2.9999999999"],
+        svec!["3"],
+        svec!["-0.0000000001"],
+    ];
+    wrk.create("in.csv", rows);
+    let mut cmd = wrk.command("frequency");
+    cmd.arg("in.csv").args(&["--limit", "0", "--bin-width", "1"]);
+
+    let mut got: Vec<Vec<String>> = wrk.read_stdout(&mut cmd);
+    got.sort();
+    let expected = vec![
+        svec!["field", "value", "count"],
+        svec!["n", "[-1, 0)", "1"],
+        svec!["n", "[2, 3)", "1"],
+        svec!["n", "[3, 4)", "1"],
+    ];
+    assert_eq!(got, expected);
+}
+
+#[test]
+fn frequency_bin_edges() {
+    let (wrk, mut cmd) = setup_numbers("frequency_bin_edges");
+    cmd.args(&["--bin-edges", "0,12,15.5"]);
+
+    let mut got: Vec<Vec<String>> = wrk.read_stdout(&mut cmd);
+    got.sort();
+    let expected = vec![
+        svec!["field", "value", "count"],
+        svec!["n", "(-inf, 0)", "1"],
+        svec!["n", "[0, 12)", "2"],
+        svec!["n", "[12, 15.5)", "1"],
+        svec!["n", "[15.5, inf)", "2"],
+        svec!["n", "n/a", "1"],
+    ];
+    assert_eq!(got, expected);
+}
+
+#[test]
+fn frequency_bin_edges_not_increasing() {
+    let (wrk, mut cmd) = setup_numbers("frequency_bin_edges_not_increasing");
+    cmd.args(&["--bin-edges", "10,0"]);
+    wrk.assert_err(&mut cmd);
+}
+
+#[test]
+fn frequency_heavy_hitters() {
+    let (wrk, mut cmd) = setup("frequency_heavy_hitters");
+    cmd.args(&["--heavy-hitters", "--select", "h1", "--limit", "1"]);
+
+    let got: Vec<Vec<String>> = wrk.read_stdout(&mut cmd);
+    let expected = vec![
+        svec!["field", "value", "count", "error"],
+        svec!["h1", "a", "3", "0"],
+    ];
+    assert_eq!(got, expected);
+}
+
+#[test]
+fn frequency_heavy_hitters_index() {
+    let wrk = Workdir::new("frequency_heavy_hitters_index");
+    let mut rows = vec![svec!["h"]];
+    for i in 0..1000 {
+        let value = match i % 4 {
+            0 | 2 => "hot".to_owned(),
+            1 => "warm".to_owned(),
+            _ => i.to_string(),
+        };
+        rows.push(vec![value]);
+    }
+    wrk.create_indexed("in.csv", rows);
+    let mut cmd = wrk.command("frequency");
+    cmd.args(&["--heavy-hitters", "--capacity", "10", "--limit", "2"])
+       .args(&["--jobs", "4"])
+       .arg("in.csv");
+
+    let got: Vec<Vec<String>> = wrk.read_stdout(&mut cmd);
+    assert_eq!(got[0], svec!["field", "value", "count", "error"]);
+    assert_eq!(got.len(), 3);
+    for (row, &(value, exact)) in got[1..].iter().zip(&[("hot", 500),
+                                                        ("warm", 250)]) {
+        assert_eq!(row[1], value);
+        let count: u64 = row[2].parse().unwrap();
+        let error: u64 = row[3].parse().unwrap();
+        assert!(count - error <= exact && exact <= count);
+    }
+}
+
+#[test]
+fn frequency_heavy_hitters_merge_ties() {
+    let wrk = Workdir::new("frequency_heavy_hitters_merge_ties");
+    let rows = vec![svec!["h"], svec!["d"], svec!["a"], svec!["c"], svec!["b"]];
+    wrk.create_indexed("in.csv", rows);
+    let mut cmd = wrk.command("frequency");
+    cmd.args(&["--heavy-hitters", "--capacity", "2", "--limit", "0"])
+       .args(&["--jobs", "2"])
+       .arg("in.csv");
+
+    let got: Vec<Vec<String>> = wrk.read_stdout(&mut cmd);
+    let expected = vec![
+        svec!["field", "value", "count", "error"],
+        svec!["h", "b", "2", "1"],
+        svec!["h", "a", "2", "1"],
+    ];
+    assert_eq!(got, expected);
+}
+
+#[test]
+fn frequency_heavy_hitters_asc() {
+    let (wrk, mut cmd) = setup("frequency_heavy_hitters_asc");
+    cmd.args(&["--heavy-hitters", "--asc"]);
+    wrk.assert_err(&mut cmd);
+}
+
 // This tests that a frequency table computed by `xsv` is always the same
 // as the frequency table computed in memory.
 #[test]
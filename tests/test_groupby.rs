@@ -0,0 +1,101 @@
+use workdir::Workdir;
+
+fn data() -> Vec<Vec<String>> {
+    vec![
+        svec!["region", "user", "revenue"],
+        svec!["east", "alice", "10"],
+        svec!["west", "bob", "5"],
+        svec!["east", "bob", "2.5"],
+        svec!["east", "alice", ""],
+        svec!["north", "carol", "7"],
+        svec!["west", "dave", "x"],
+    ]
+}
+
+fn setup(name: &str, indexed: bool) -> Workdir {
+    let wrk = Workdir::new(name);
+    if indexed {
+        wrk.create_indexed("in.csv", data());
+    } else {
+        wrk.create("in.csv", data());
+    }
+    wrk
+}
+
+fn groupby(name: &str, indexed: bool) {
+    let wrk = setup(name, indexed);
+    let mut cmd = wrk.command("groupby");
+    cmd.arg("region")
+       .arg("count(),sum(revenue),count_distinct(user),concat(user)")
+       .arg("in.csv");
+    if indexed {
+        cmd.args(&["--jobs", "4"]);
+    }
+
+    let got: Vec<Vec<String>> = wrk.read_stdout(&mut cmd);
+    let expected = vec![
+        svec!["region", "count()", "sum(revenue)", "count_distinct(user)",
+              "concat(user)"],
+        svec!["east", "3", "12.5", "2", "alice;bob;alice"],
+        svec!["west", "2", "", "2", "bob;dave"],
+        svec!["north", "1", "7", "1", "carol"],
+    ];
+    assert_eq!(got, expected);
+}
+
+#[test]
+fn groupby_sequential() {
+    groupby("groupby_sequential", false);
+}
+
+#[test]
+fn groupby_parallel() {
+    groupby("groupby_parallel", true);
+}
+
+#[test]
+fn groupby_stats() {
+    let wrk = setup("groupby_stats", false);
+    let mut cmd = wrk.command("groupby");
+    cmd.arg("1")
+       .arg("min(3), max(3), mean(3), first(3), last(3), count(3)")
+       .arg("in.csv");
+
+    let got: Vec<Vec<String>> = wrk.read_stdout(&mut cmd);
+    let expected = vec![
+        svec!["region", "min(3)", "max(3)", "mean(3)", "first(3)",
+              "last(3)", "count(3)"],
+        svec!["east", "2.5", "10", "6.25", "10", "2.5", "2"],
+        svec!["west", "5", "x", "5", "5", "x", "2"],
+        svec!["north", "7", "7", "7", "7", "7", "1"],
+    ];
+    assert_eq!(got, expected);
+}
+
+#[test]
+fn groupby_multiple_keys() {
+    let wrk = setup("groupby_multiple_keys", false);
+    let mut cmd = wrk.command("groupby");
+    cmd.args(&["region,user", "stddev(revenue)", "in.csv"]);
+
+    let got: Vec<Vec<String>> = wrk.read_stdout(&mut cmd);
+    assert_eq!(got[0], svec!["region", "user", "stddev(revenue)"]);
+    assert_eq!(got[1], svec!["east", "alice", "0"]);
+    assert_eq!(got.len(), 6);
+}
+
+#[test]
+fn groupby_unknown_function() {
+    let wrk = setup("groupby_unknown_function", false);
+    let mut cmd = wrk.command("groupby");
+    cmd.args(&["region", "median(revenue)", "in.csv"]);
+    wrk.assert_err(&mut cmd);
+}
+
+#[test]
+fn groupby_missing_column() {
+    let wrk = setup("groupby_missing_column", false);
+    let mut cmd = wrk.command("groupby");
+    cmd.args(&["region", "sum()", "in.csv"]);
+    wrk.assert_err(&mut cmd);
+}
@@ -25,3 +25,37 @@ fn future_time(ft: FileTime) -> FileTime {
     let secs = ft.seconds_relative_to_1970();
     FileTime::from_seconds_since_1970(secs + 10_000, 0)
 }
+
+#[test]
+fn index_columns() {
+    let wrk = Workdir::new("index_columns");
+    wrk.create("in.csv", vec![
+        svec!["a", "b", "c"], svec!["1", "2", "3"],
+    ]);
+    let mut cmd = wrk.command("index");
+    cmd.args(&["--columns", "c,a", "in.csv"]);
+    wrk.run(&mut cmd);
+
+    assert!(fs::metadata(&wrk.path("in.csv.3-1.vidx")).is_ok());
+}
+
+#[test]
+fn index_columns_outdated() {
+    let wrk = Workdir::new("index_columns_outdated");
+    wrk.create("a.csv", vec![svec!["k", "x"], svec!["1", "a"]]);
+    wrk.create("b.csv", vec![svec!["k", "y"], svec!["1", "b"]]);
+    let mut cmd = wrk.command("index");
+    cmd.args(&["--columns", "k", "b.csv"]);
+    wrk.run(&mut cmd);
+
+    let md = fs::metadata(&wrk.path("b.csv.1.vidx")).unwrap();
+    set_file_times(
+        &wrk.path("b.csv"),
+        future_time(FileTime::from_last_modification_time(&md)),
+        future_time(FileTime::from_last_access_time(&md)),
+    ).unwrap();
+
+    let mut cmd = wrk.command("join");
+    cmd.args(&["k", "a.csv", "k", "b.csv"]);
+    wrk.assert_err(&mut cmd);
+}
@@ -184,3 +184,671 @@ fn join_cross_no_headers() {
     ];
     assert_eq!(got, expected);
 }
+
+fn setup_sorted(name: &str) -> Workdir {
+    let wrk = Workdir::new(name);
+    wrk.create("cities.csv", vec![
+        svec!["city", "state"],
+        svec!["", "XX"],
+        svec!["Boston", "MA"],
+        svec!["Buffalo", "NY"],
+        svec!["New York", "NY"],
+        svec!["San Francisco", "CA"],
+    ]);
+    wrk.create("places.csv", vec![
+        svec!["city", "place"],
+        svec!["", "Nowhere"],
+        svec!["Boston", "Logan Airport"],
+        svec!["Boston", "Boston Garden"],
+        svec!["Buffalo", "Ralph Wilson Stadium"],
+        svec!["Orlando", "Disney World"],
+    ]);
+    wrk
+}
+
+fn join_merge_matches_hash(name: &str, flag: Option<&str>) {
+    let wrk = setup_sorted(name);
+
+    let mut cmd = wrk.command("join");
+    cmd.args(&["city", "cities.csv", "city", "places.csv"]);
+    if let Some(flag) = flag { cmd.arg(flag); }
+    let mut expected: Vec<Vec<String>> = wrk.read_stdout(&mut cmd);
+
+    let mut cmd = wrk.command("join");
+    cmd.arg("--merge").args(&["city", "cities.csv", "city", "places.csv"]);
+    if let Some(flag) = flag { cmd.arg(flag); }
+    let mut got: Vec<Vec<String>> = wrk.read_stdout(&mut cmd);
+
+    // The order of rows without a match may differ between the two
+    // strategies, so only compare the sets of rows.
+    assert_eq!(got[0], expected[0]);
+    got.sort();
+    expected.sort();
+    assert_eq!(got, expected);
+}
+
+#[test]
+fn join_merge_inner() {
+    join_merge_matches_hash("join_merge_inner", None);
+}
+
+#[test]
+fn join_merge_left() {
+    join_merge_matches_hash("join_merge_left", Some("--left"));
+}
+
+#[test]
+fn join_merge_right() {
+    join_merge_matches_hash("join_merge_right", Some("--right"));
+}
+
+#[test]
+fn join_merge_full() {
+    join_merge_matches_hash("join_merge_full", Some("--full"));
+}
+
+#[test]
+fn join_merge_nulls() {
+    join_merge_matches_hash("join_merge_nulls", Some("--nulls"));
+}
+
+#[test]
+fn join_merge_order() {
+    let wrk = setup_sorted("join_merge_order");
+    let mut cmd = wrk.command("join");
+    cmd.args(&["--merge", "--full"]);
+    cmd.args(&["city", "cities.csv", "city", "places.csv"]);
+
+    let got: Vec<Vec<String>> = wrk.read_stdout(&mut cmd);
+    let expected = vec![
+        svec!["city", "state", "city", "place"],
+        svec!["", "XX", "", ""],
+        svec!["", "", "", "Nowhere"],
+        svec!["Boston", "MA", "Boston", "Logan Airport"],
+        svec!["Boston", "MA", "Boston", "Boston Garden"],
+        svec!["Buffalo", "NY", "Buffalo", "Ralph Wilson Stadium"],
+        svec!["New York", "NY", "", ""],
+        svec!["", "", "Orlando", "Disney World"],
+        svec!["San Francisco", "CA", "", ""],
+    ];
+    assert_eq!(got, expected);
+}
+
+#[test]
+fn join_merge_unsorted() {
+    let wrk = setup("join_merge_unsorted", true);
+    let mut cmd = wrk.command("join");
+    cmd.arg("--merge").args(&["city", "cities.csv", "city", "places.csv"]);
+    wrk.assert_err(&mut cmd);
+}
+
+fn setup_sorted_by_xsv(name: &str, order: &str) -> Workdir {
+    let wrk = Workdir::new(name);
+    wrk.create("cities.csv", vec![
+        svec!["city", "state"],
+        svec!["boston", "MA"],
+        svec![" Buffalo", "NY"],
+        svec!["Boston", "MA"],
+    ]);
+    wrk.create("places.csv", vec![
+        svec!["city", "place"],
+        svec!["Boston", "Boston Garden"],
+        svec![" Buffalo", "Ralph Wilson Stadium"],
+        svec!["boston", "Boston Common"],
+    ]);
+    for input in &["cities", "places"] {
+        let mut cmd = wrk.command("sort");
+        cmd.args(&["-s", "city", "--order", order])
+           .arg(format!("{}.csv", input))
+           .args(&["-o", &format!("{}-sorted.csv", input)]);
+        wrk.run(&mut cmd);
+    }
+    wrk
+}
+
+#[test]
+fn join_merge_sorted_by_xsv() {
+    let wrk = setup_sorted_by_xsv("join_merge_sorted_by_xsv", "text");
+    let mut cmd = wrk.command("join");
+    cmd.arg("--merge");
+    cmd.args(&["city", "cities-sorted.csv", "city", "places-sorted.csv"]);
+
+    let got: Vec<Vec<String>> = wrk.read_stdout(&mut cmd);
+    let expected = vec![
+        svec!["city", "state", "city", "place"],
+        svec![" Buffalo", "NY", " Buffalo", "Ralph Wilson Stadium"],
+        svec!["Boston", "MA", "Boston", "Boston Garden"],
+        svec!["boston", "MA", "boston", "Boston Common"],
+    ];
+    assert_eq!(got, expected);
+}
+
+#[test]
+fn join_merge_sorted_by_xsv_no_case() {
+    let wrk = setup_sorted_by_xsv("join_merge_sorted_by_xsv_no_case",
+                                  "nocase");
+    let mut cmd = wrk.command("join");
+    cmd.args(&["--merge", "--no-case"]);
+    cmd.args(&["city", "cities-sorted.csv", "city", "places-sorted.csv"]);
+
+    let got: Vec<Vec<String>> = wrk.read_stdout(&mut cmd);
+    let expected = vec![
+        svec!["city", "state", "city", "place"],
+        svec![" Buffalo", "NY", " Buffalo", "Ralph Wilson Stadium"],
+        svec!["boston", "MA", "Boston", "Boston Garden"],
+        svec!["boston", "MA", "boston", "Boston Common"],
+        svec!["Boston", "MA", "Boston", "Boston Garden"],
+        svec!["Boston", "MA", "boston", "Boston Common"],
+    ];
+    assert_eq!(got, expected);
+}
+
+#[test]
+fn join_merge_normalize() {
+    let wrk = setup_sorted_by_xsv("join_merge_normalize", "text");
+    for input in &["cities", "places"] {
+        let mut cmd = wrk.command("sort");
+        cmd.args(&["-s", "city", "--normalize", "trim,lower"])
+           .arg(format!("{}.csv", input))
+           .args(&["-o", &format!("{}-normalized.csv", input)]);
+        wrk.run(&mut cmd);
+    }
+    let mut cmd = wrk.command("join");
+    cmd.args(&["--merge", "--normalize", "trim,lower"]);
+    cmd.args(&["city", "cities-normalized.csv",
+               "city", "places-normalized.csv"]);
+
+    let got: Vec<Vec<String>> = wrk.read_stdout(&mut cmd);
+    let expected = vec![
+        svec!["city", "state", "city", "place"],
+        svec!["boston", "MA", "Boston", "Boston Garden"],
+        svec!["boston", "MA", "boston", "Boston Common"],
+        svec!["Boston", "MA", "Boston", "Boston Garden"],
+        svec!["Boston", "MA", "boston", "Boston Common"],
+        svec![" Buffalo", "NY", " Buffalo", "Ralph Wilson Stadium"],
+    ];
+    assert_eq!(got, expected);
+}
+
+#[test]
+fn join_merge_key_regex_unsorted() {
+    // Sorted by the whole values, the keys matched by the regex are not
+    // in order.
+    let wrk = setup_sorted_by_xsv("join_merge_key_regex_unsorted", "text");
+    let mut cmd = wrk.command("join");
+    cmd.args(&["--merge", "--key-regex", "[a-z]+$"]);
+    cmd.args(&["city", "cities-sorted.csv", "city", "places-sorted.csv"]);
+    wrk.assert_err(&mut cmd);
+}
+
+#[test]
+fn join_merge_cross() {
+    let wrk = setup_sorted("join_merge_cross");
+    let mut cmd = wrk.command("join");
+    cmd.args(&["--merge", "--cross"]);
+    cmd.args(&["city", "cities.csv", "city", "places.csv"]);
+    wrk.assert_err(&mut cmd);
+}
+
+#[test]
+fn join_left_semi() {
+    let wrk = setup("join_left_semi", true);
+    let mut cmd = wrk.command("join");
+    cmd.arg("--left-semi").args(&["city", "cities.csv", "city", "places.csv"]);
+
+    let got: Vec<Vec<String>> = wrk.read_stdout(&mut cmd);
+    let expected = vec![
+        svec!["city", "state"],
+        svec!["Boston", "MA"],
+        svec!["Buffalo", "NY"],
+    ];
+    assert_eq!(got, expected);
+}
+
+#[test]
+fn join_left_semi_no_headers() {
+    let wrk = setup("join_left_semi_no_headers", false);
+    let mut cmd = wrk.command("join");
+    cmd.args(&["--left-semi", "--no-headers"]);
+    cmd.args(&["1", "cities.csv", "1", "places.csv"]);
+
+    let got: Vec<Vec<String>> = wrk.read_stdout(&mut cmd);
+    let expected = vec![
+        svec!["Boston", "MA"],
+        svec!["Buffalo", "NY"],
+    ];
+    assert_eq!(got, expected);
+}
+
+#[test]
+fn join_left_anti() {
+    let wrk = setup("join_left_anti", true);
+    let mut cmd = wrk.command("join");
+    cmd.arg("--left-anti").args(&["city", "cities.csv", "city", "places.csv"]);
+
+    let got: Vec<Vec<String>> = wrk.read_stdout(&mut cmd);
+    let expected = vec![
+        svec!["city", "state"],
+        svec!["New York", "NY"],
+        svec!["San Francisco", "CA"],
+    ];
+    assert_eq!(got, expected);
+}
+
+#[test]
+fn join_left_semi_conflict() {
+    let wrk = setup("join_left_semi_conflict", true);
+    let mut cmd = wrk.command("join");
+    cmd.args(&["--left-semi", "--left-anti"]);
+    cmd.args(&["city", "cities.csv", "city", "places.csv"]);
+    wrk.assert_err(&mut cmd);
+}
+
+fn setup_three(name: &str) -> Workdir {
+    let wrk = setup(name, true);
+    wrk.create("teams.csv", vec![
+        svec!["city", "team"],
+        svec!["Boston", "Red Sox"],
+        svec!["Boston", "Celtics"],
+        svec!["New York", "Yankees"],
+        svec!["Buffalo", "Bills"],
+    ]);
+    wrk
+}
+
+#[test]
+fn join_three_inner() {
+    let wrk = setup_three("join_three_inner");
+    let mut cmd = wrk.command("join");
+    cmd.args(&["city", "cities.csv", "city", "places.csv"]);
+    cmd.args(&["city", "teams.csv"]);
+
+    let got: Vec<Vec<String>> = wrk.read_stdout(&mut cmd);
+    let expected = vec![
+        svec!["city", "state", "city", "place", "city", "team"],
+        svec!["Boston", "MA", "Boston", "Logan Airport", "Boston", "Red Sox"],
+        svec!["Boston", "MA", "Boston", "Logan Airport", "Boston", "Celtics"],
+        svec!["Boston", "MA", "Boston", "Boston Garden", "Boston", "Red Sox"],
+        svec!["Boston", "MA", "Boston", "Boston Garden", "Boston", "Celtics"],
+        svec!["Buffalo", "NY", "Buffalo", "Ralph Wilson Stadium",
+              "Buffalo", "Bills"],
+    ];
+    assert_eq!(got, expected);
+}
+
+#[test]
+fn join_three_left_prefix() {
+    let wrk = setup_three("join_three_left_prefix");
+    let mut cmd = wrk.command("join");
+    cmd.args(&["--left", "--prefix", ",p_,t_"]);
+    cmd.args(&["city", "cities.csv", "city", "places.csv"]);
+    cmd.args(&["city", "teams.csv"]);
+
+    let got: Vec<Vec<String>> = wrk.read_stdout(&mut cmd);
+    let expected = vec![
+        svec!["city", "state", "p_city", "p_place", "t_city", "t_team"],
+        svec!["Boston", "MA", "Boston", "Logan Airport", "Boston", "Red Sox"],
+        svec!["Boston", "MA", "Boston", "Logan Airport", "Boston", "Celtics"],
+        svec!["Boston", "MA", "Boston", "Boston Garden", "Boston", "Red Sox"],
+        svec!["Boston", "MA", "Boston", "Boston Garden", "Boston", "Celtics"],
+        svec!["New York", "NY", "", "", "New York", "Yankees"],
+        svec!["San Francisco", "CA", "", "", "", ""],
+        svec!["Buffalo", "NY", "Buffalo", "Ralph Wilson Stadium",
+              "Buffalo", "Bills"],
+    ];
+    assert_eq!(got, expected);
+}
+
+#[test]
+fn join_suffix() {
+    let wrk = setup("join_suffix", true);
+    let mut cmd = wrk.command("join");
+    cmd.args(&["--suffix", "_1,_2"]);
+    cmd.args(&["city", "cities.csv", "city", "places.csv"]);
+
+    let got: Vec<Vec<String>> = wrk.read_stdout(&mut cmd);
+    assert_eq!(got[0], svec!["city_1", "state_1", "city_2", "place_2"]);
+}
+
+#[test]
+fn join_prefix_wrong_count() {
+    let wrk = setup("join_prefix_wrong_count", true);
+    let mut cmd = wrk.command("join");
+    cmd.args(&["--prefix", "a_"]);
+    cmd.args(&["city", "cities.csv", "city", "places.csv"]);
+    wrk.assert_err(&mut cmd);
+}
+
+#[test]
+fn join_three_right() {
+    let wrk = setup_three("join_three_right");
+    let mut cmd = wrk.command("join");
+    cmd.arg("--right");
+    cmd.args(&["city", "cities.csv", "city", "places.csv"]);
+    cmd.args(&["city", "teams.csv"]);
+    wrk.assert_err(&mut cmd);
+}
+
+fn setup_keys(name: &str) -> Workdir {
+    let people = vec![
+        svec!["name", "id"],
+        svec!["José", "007"],
+        svec!["Zoë", "ID-12"],
+    ];
+    let scores = vec![
+        svec!["name", "id", "score"],
+        svec!["Jose\u{301}", "7", "10"],
+        svec!["Zoe", "12", "20"],
+    ];
+    let wrk = Workdir::new(name);
+    wrk.create("people.csv", people);
+    wrk.create("scores.csv", scores);
+    wrk
+}
+
+#[test]
+fn join_no_case_full_lowercase() {
+    let wrk = Workdir::new("join_no_case_full_lowercase");
+    wrk.create("cities.csv", vec![svec!["city"], svec!["İstanbul"]]);
+    wrk.create("visits.csv", vec![
+        svec!["city", "n"],
+        svec!["istanbul", "1"],
+        svec!["i\u{307}stanbul", "2"],
+    ]);
+    let mut cmd = wrk.command("join");
+    cmd.arg("--no-case");
+    cmd.args(&["city", "cities.csv", "city", "visits.csv"]);
+
+    // 'İ' lowercases to 'i' followed by a combining dot above.
+    let got: Vec<Vec<String>> = wrk.read_stdout(&mut cmd);
+    let expected = vec![
+        svec!["city", "city", "n"],
+        svec!["İstanbul", "i\u{307}stanbul", "2"],
+    ];
+    assert_eq!(got, expected);
+}
+
+#[test]
+fn join_normalize_nfc() {
+    let wrk = setup_keys("join_normalize_nfc");
+    let mut cmd = wrk.command("join");
+    cmd.args(&["--normalize", "nfc"]);
+    cmd.args(&["name", "people.csv", "name", "scores.csv"]);
+
+    let got: Vec<Vec<String>> = wrk.read_stdout(&mut cmd);
+    let expected = vec![
+        svec!["name", "id", "name", "id", "score"],
+        svec!["José", "007", "Jose\u{301}", "7", "10"],
+    ];
+    assert_eq!(got, expected);
+}
+
+#[test]
+fn join_normalize_unaccent() {
+    let wrk = setup_keys("join_normalize_unaccent");
+    let mut cmd = wrk.command("join");
+    cmd.args(&["--normalize", "unaccent"]);
+    cmd.args(&["name", "people.csv", "name", "scores.csv"]);
+
+    let got: Vec<Vec<String>> = wrk.read_stdout(&mut cmd);
+    assert_eq!(got.len(), 3);
+    assert_eq!(got[2], svec!["Zoë", "ID-12", "Zoe", "12", "20"]);
+}
+
+#[test]
+fn join_normalize_numeric() {
+    let wrk = setup_keys("join_normalize_numeric");
+    let mut cmd = wrk.command("join");
+    cmd.args(&["--normalize", "numeric"]);
+    cmd.args(&["id", "people.csv", "id", "scores.csv"]);
+
+    let got: Vec<Vec<String>> = wrk.read_stdout(&mut cmd);
+    let expected = vec![
+        svec!["name", "id", "name", "id", "score"],
+        svec!["José", "007", "Jose\u{301}", "7", "10"],
+    ];
+    assert_eq!(got, expected);
+}
+
+#[test]
+fn join_key_regex() {
+    let wrk = setup_keys("join_key_regex");
+    let mut cmd = wrk.command("join");
+    cmd.args(&["--key-regex", "([0-9]+)$", "--normalize", "numeric"]);
+    cmd.args(&["id", "people.csv", "id", "scores.csv"]);
+
+    let got: Vec<Vec<String>> = wrk.read_stdout(&mut cmd);
+    assert_eq!(got.len(), 3);
+    assert_eq!(got[2], svec!["Zoë", "ID-12", "Zoe", "12", "20"]);
+}
+
+#[test]
+fn join_normalize_unknown() {
+    let wrk = setup_keys("join_normalize_unknown");
+    let mut cmd = wrk.command("join");
+    cmd.args(&["--normalize", "soundex"]);
+    cmd.args(&["id", "people.csv", "id", "scores.csv"]);
+    wrk.assert_err(&mut cmd);
+}
+
+fn setup_asof(name: &str) -> Workdir {
+    let trades = vec![
+        svec!["sym", "time", "qty"],
+        svec!["A", "2018-06-01T09:30:05", "10"],
+        svec!["B", "2018-06-01T09:30:05", "20"],
+        svec!["A", "2018-06-01T09:30:00", "30"],
+        svec!["A", "2018-06-01T09:29:00", "40"],
+    ];
+    let quotes = vec![
+        svec!["sym", "time", "price"],
+        svec!["A", "2018-06-01T09:30:00", "1.5"],
+        svec!["A", "2018-06-01T09:30:03", "1.6"],
+        svec!["A", "2018-06-01T09:30:09", "1.7"],
+        svec!["B", "2018-06-01T09:30:06", "2.5"],
+    ];
+    let wrk = Workdir::new(name);
+    wrk.create("trades.csv", trades);
+    wrk.create("quotes.csv", quotes);
+    wrk
+}
+
+#[test]
+fn join_asof() {
+    let wrk = setup_asof("join_asof");
+    let mut cmd = wrk.command("join");
+    cmd.args(&["--asof", "sym,time", "trades.csv", "sym,time", "quotes.csv"]);
+
+    let got: Vec<Vec<String>> = wrk.read_stdout(&mut cmd);
+    let expected = vec![
+        svec!["sym", "time", "qty", "sym", "time", "price"],
+        svec!["A", "2018-06-01T09:30:05", "10",
+              "A", "2018-06-01T09:30:03", "1.6"],
+        svec!["A", "2018-06-01T09:30:00", "30",
+              "A", "2018-06-01T09:30:00", "1.5"],
+    ];
+    assert_eq!(got, expected);
+}
+
+#[test]
+fn join_asof_left() {
+    let wrk = setup_asof("join_asof_left");
+    let mut cmd = wrk.command("join");
+    cmd.args(&["--asof", "--left"]);
+    cmd.args(&["sym,time", "trades.csv", "sym,time", "quotes.csv"]);
+
+    let got: Vec<Vec<String>> = wrk.read_stdout(&mut cmd);
+    assert_eq!(got.len(), 5);
+    assert_eq!(got[2], svec!["B", "2018-06-01T09:30:05", "20",
+                             "", "", ""]);
+    assert_eq!(got[4], svec!["A", "2018-06-01T09:29:00", "40",
+                             "", "", ""]);
+}
+
+#[test]
+fn join_asof_numeric() {
+    let wrk = Workdir::new("join_asof_numeric");
+    wrk.create("a.csv", vec![svec!["t"], svec!["9"], svec!["10"]]);
+    wrk.create("b.csv", vec![
+        svec!["t", "v"], svec!["2", "x"], svec!["10", "y"],
+    ]);
+    let mut cmd = wrk.command("join");
+    cmd.args(&["--asof", "t", "a.csv", "t", "b.csv"]);
+
+    let got: Vec<Vec<String>> = wrk.read_stdout(&mut cmd);
+    let expected = vec![
+        svec!["t", "t", "v"],
+        svec!["9", "2", "x"],
+        svec!["10", "10", "y"],
+    ];
+    assert_eq!(got, expected);
+}
+
+#[test]
+fn join_asof_dates() {
+    let wrk = Workdir::new("join_asof_dates");
+    wrk.create("a.csv", vec![
+        svec!["t"],
+        svec!["2024-01-10"],
+        svec!["2024/01/06 12:00"],
+        svec!["2024-01-09T23:00:00-02:00"],
+    ]);
+    wrk.create("b.csv", vec![
+        svec!["t", "v"],
+        svec!["2024-1-5", "x"],
+        svec!["2024-01-09 23:30", "y"],
+        svec!["2024-01-10T00:30:00Z", "z"],
+    ]);
+    let mut cmd = wrk.command("join");
+    cmd.args(&["--asof", "t", "a.csv", "t", "b.csv"]);
+
+    // As text, '2024-1-5' would sort after all of the other dates.
+    let got: Vec<Vec<String>> = wrk.read_stdout(&mut cmd);
+    let expected = vec![
+        svec!["t", "t", "v"],
+        svec!["2024-01-10", "2024-01-09 23:30", "y"],
+        svec!["2024/01/06 12:00", "2024-1-5", "x"],
+        svec!["2024-01-09T23:00:00-02:00", "2024-01-10T00:30:00Z", "z"],
+    ];
+    assert_eq!(got, expected);
+}
+
+#[test]
+fn join_interval() {
+    let wrk = Workdir::new("join_interval");
+    wrk.create("events.csv", vec![
+        svec!["id", "t"],
+        svec!["e1", "5"],
+        svec!["e2", "10"],
+        svec!["e3", "20"],
+    ]);
+    wrk.create("periods.csv", vec![
+        svec!["name", "start", "end"],
+        svec!["p1", "0", "10"],
+        svec!["p2", "8", "15"],
+    ]);
+    let mut cmd = wrk.command("join");
+    cmd.args(&["--interval", "--left"]);
+    cmd.args(&["t", "events.csv", "start,end", "periods.csv"]);
+
+    let got: Vec<Vec<String>> = wrk.read_stdout(&mut cmd);
+    let expected = vec![
+        svec!["id", "t", "name", "start", "end"],
+        svec!["e1", "5", "p1", "0", "10"],
+        svec!["e2", "10", "p2", "8", "15"],
+        svec!["e3", "20", "", "", ""],
+    ];
+    assert_eq!(got, expected);
+}
+
+#[test]
+fn join_interval_wrong_columns() {
+    let wrk = Workdir::new("join_interval_wrong_columns");
+    wrk.create("a.csv", vec![svec!["t"], svec!["1"]]);
+    wrk.create("b.csv", vec![svec!["start", "end"], svec!["0", "2"]]);
+    let mut cmd = wrk.command("join");
+    cmd.args(&["--interval", "t", "a.csv", "start", "b.csv"]);
+    wrk.assert_err(&mut cmd);
+}
+
+#[test]
+fn join_value_index() {
+    let wrk = setup("join_value_index", true);
+    let mut cmd = wrk.command("index");
+    cmd.args(&["--columns", "city", "places.csv"]);
+    wrk.run(&mut cmd);
+
+    let mut cmd = wrk.command("join");
+    cmd.args(&["--left", "city", "cities.csv", "city", "places.csv"]);
+    let got: Vec<Vec<String>> = wrk.read_stdout(&mut cmd);
+
+    let mut cmd = wrk.command("join");
+    cmd.args(&["--left", "city", "cities.csv", "city", "places.csv"]);
+    cmd.args(&["city", "places.csv"]);
+    let three: Vec<Vec<String>> = wrk.read_stdout(&mut cmd);
+
+    let expected = make_rows(true, vec![
+        svec!["Boston", "MA", "Boston", "Logan Airport"],
+        svec!["Boston", "MA", "Boston", "Boston Garden"],
+        svec!["New York", "NY", "", ""],
+        svec!["San Francisco", "CA", "", ""],
+        svec!["Buffalo", "NY", "Buffalo", "Ralph Wilson Stadium"],
+    ]);
+    assert_eq!(got, expected);
+    assert_eq!(three.len(), 8);
+}
+
+#[test]
+fn join_value_index_probe() {
+    // Test directories are reused, so compare against a directory that never
+    // has an index instead of joining before indexing.
+    let plain = Workdir::new("join_value_index_probe_plain");
+    let wrk = Workdir::new("join_value_index_probe");
+    let mut left = vec![svec!["k", "a"]];
+    let mut right = vec![svec!["k", "b"]];
+    for i in 0..60 {
+        left.push(vec![format!("k{}", i % 20), i.to_string()]);
+        // Pad some keys with whitespace and upper case some others, which
+        // an index must find as the same keys.
+        let key = match i % 3 {
+            0 => format!(" k{} ", i % 25),
+            1 => format!("K{}", i % 25),
+            _ => format!("k{}", i % 25),
+        };
+        right.push(vec![key, i.to_string()]);
+    }
+    right.push(svec!["", "empty"]);
+    left.push(svec!["", "empty"]);
+    for w in &[&plain, &wrk] {
+        w.create("left.csv", left.clone());
+        w.create("right.csv", right.clone());
+    }
+
+    let joins: &[&[&str]] = &[
+        &[],
+        &["--left"],
+        &["--right"],
+        &["--full"],
+        &["--left-semi"],
+        &["--left-anti"],
+        &["--nulls"],
+        &["--no-case"],
+        &["--no-case", "--full"],
+    ];
+    let run = |wrk: &Workdir, flags: &[&str]| -> Vec<Vec<String>> {
+        let mut cmd = wrk.command("join");
+        cmd.args(flags).args(&["k", "left.csv", "k", "right.csv"]);
+        wrk.read_stdout(&mut cmd)
+    };
+
+    let mut cmd = wrk.command("index");
+    cmd.args(&["--columns", "k", "right.csv"]);
+    wrk.run(&mut cmd);
+    let mut cmd = wrk.command("index");
+    cmd.args(&["--columns", "k", "left.csv"]);
+    wrk.run(&mut cmd);
+    for flags in joins {
+        let expected = run(&plain, flags);
+        assert!(expected.len() > 1);
+        assert_eq!(run(&wrk, flags), expected, "join {:?}", flags);
+    }
+}
@@ -149,3 +149,38 @@ fn search_invert_match_no_headers() {
     ];
     assert_eq!(got, expected);
 }
+
+#[test]
+fn search_value_index() {
+    let mut rows = vec![svec!["h1", "h2"]];
+    for i in 0..30 {
+        rows.push(vec![format!("v{}", i % 7), format!("w{}", i % 4)]);
+    }
+    // Test directories are reused, so compare against a directory that never
+    // has an index instead of searching before indexing.
+    let plain = Workdir::new("search_value_index_plain");
+    plain.create("data.csv", rows.clone());
+    let wrk = Workdir::new("search_value_index");
+    wrk.create("data.csv", rows);
+    let mut cmd = wrk.command("index");
+    cmd.args(&["--columns", "h1", "data.csv"]);
+    wrk.run(&mut cmd);
+
+    let searches: &[&[&str]] = &[
+        &["^v[13]$"],
+        &["V1", "-i"],
+        &["^v[13]$", "--invert-match"],
+        &["^v[13]$", "--no-headers"],
+        &["^h", "--no-headers"],
+    ];
+    for args in searches {
+        let run = |wrk: &Workdir| -> Vec<Vec<String>> {
+            let mut cmd = wrk.command("search");
+            cmd.args(*args).args(&["--select", "1", "data.csv"]);
+            wrk.read_stdout(&mut cmd)
+        };
+        let expected = run(&plain);
+        assert!(!expected.is_empty());
+        assert_eq!(run(&wrk), expected, "search {:?}", args);
+    }
+}
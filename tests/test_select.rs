@@ -104,6 +104,16 @@ select_test!(select_range_no_end_cat, "h4-,h1", "4-,1",
 select_test!(select_range_no_start_cat, "-h2,h1[1]", "-2,5",
              ["h1", "h2", "h1"], ["a", "b", "e"]);
 
+select_test!(select_regex, "/^h[12]$/", "1-2,5",
+             ["h1", "h2", "h1"], ["a", "b", "e"]);
+select_test!(select_regex_comma_dash, "/^h[1-9]{1,2}$/,h2", "1-2,4-5,2",
+             ["h1", "h2", "h4", "h1", "h2"], ["a", "b", "d", "e", "b"]);
+select_test!(select_regex_not, "!/1/", "!1,5",
+             ["h2", "h[]3", "h4"], ["b", "c", "d"]);
+select_test!(select_glob, "*3", "3", ["h[]3"], ["c"]);
+select_test!(select_glob_cat, "h4,*1", "4,1,5",
+             ["h4", "h1", "h1"], ["d", "a", "e"]);
+
 select_test_err!(select_err_unknown_header, "dne");
 select_test_err!(select_err_oob_low, "0");
 select_test_err!(select_err_oob_high, "6");
@@ -114,3 +124,121 @@ select_test_err!(select_err_idx_not_int_2, "h1[a]");
 select_test_err!(select_err_unclosed_quote, r#""h1"#);
 select_test_err!(select_err_unclosed_bracket, r#""h1"[1"#);
 select_test_err!(select_err_expected_end_of_field, "a-b-");
+select_test_err!(select_err_regex_no_match, "/^x/");
+select_test_err!(select_err_regex_unclosed, "/h1");
+select_test_err!(select_err_regex_invalid, "/(/");
+select_test_err!(select_err_regex_range, "/h1/-h4");
+select_test_err!(select_err_glob_no_match, "x*");
+select_test_err!(select_err_type_unknown, ":number");
+select_test_err!(select_err_type_no_match, ":numeric");
+select_test_err!(select_err_type_range, ":string-h4");
+
+#[test]
+fn select_regex_no_headers() {
+    let wrk = Workdir::new("select_regex_no_headers");
+    wrk.create("data.csv", data(false));
+    let mut cmd = wrk.command("select");
+    cmd.arg("--no-headers").arg("/h/").arg("data.csv");
+    wrk.assert_err(&mut cmd);
+}
+
+#[test]
+fn select_quoted_pattern_syntax() {
+    let wrk = Workdir::new("select_quoted_pattern_syntax");
+    wrk.create("data.csv", vec![
+        svec!["a*b", "/x/", "ab", "axb", "x"],
+        svec!["1", "2", "3", "4", "5"],
+    ]);
+    let select = |sel: &str| -> Vec<Vec<String>> {
+        let mut cmd = wrk.command("select");
+        cmd.arg("--").arg(sel).arg("data.csv");
+        wrk.read_stdout(&mut cmd)
+    };
+
+    // Quoted names are matched literally, even with pattern syntax.
+    assert_eq!(select(r#""a*b","/x/""#),
+               [svec!["a*b", "/x/"], svec!["1", "2"]]);
+    // Unquoted, they are a glob and a regex.
+    assert_eq!(select("a*b"),
+               [svec!["a*b", "ab", "axb"], svec!["1", "3", "4"]]);
+    assert_eq!(select("/x/"),
+               [svec!["/x/", "axb", "x"], svec!["2", "4", "5"]]);
+}
+
+#[test]
+fn select_glob_dash() {
+    let wrk = Workdir::new("select_glob_dash");
+    wrk.create("data.csv", vec![
+        svec!["user-id", "name", "order-id", "id"],
+        svec!["1", "a", "2", "3"],
+    ]);
+    let mut cmd = wrk.command("select");
+    cmd.arg("*-id,name").arg("data.csv");
+
+    let got: Vec<Vec<String>> = wrk.read_stdout(&mut cmd);
+    let expected = vec![
+        svec!["user-id", "order-id", "name"],
+        svec!["1", "2", "a"],
+    ];
+    assert_eq!(got, expected);
+}
+
+fn setup_types(wrk: &Workdir) {
+    wrk.create("data.csv", vec![
+        svec!["n", "x", "when", "ok", "name", "empty"],
+        svec!["1", "1.5", "2018-01-31", "yes", "a", ""],
+        svec!["2", "", "2018-02-01T10:00:00", "no", "1", ""],
+    ]);
+}
+
+#[test]
+fn select_types() {
+    let wrk = Workdir::new("select_types");
+    setup_types(&wrk);
+    let select = |sel: &str| -> Vec<String> {
+        let mut cmd = wrk.command("select");
+        cmd.arg("--").arg(sel).arg("data.csv");
+        let mut rows: Vec<Vec<String>> = wrk.read_stdout(&mut cmd);
+        rows.remove(0)
+    };
+
+    assert_eq!(select(":numeric"), svec!["n", "x"]);
+    assert_eq!(select(":integer"), svec!["n"]);
+    assert_eq!(select(":float"), svec!["x"]);
+    assert_eq!(select(":date"), svec!["when"]);
+    assert_eq!(select(":boolean"), svec!["ok"]);
+    assert_eq!(select(":string,n"), svec!["name", "n"]);
+    assert_eq!(select("!:numeric"), svec!["when", "ok", "name", "empty"]);
+}
+
+#[test]
+fn select_types_no_headers() {
+    let wrk = Workdir::new("select_types_no_headers");
+    setup_types(&wrk);
+    let mut cmd = wrk.command("select");
+    cmd.args(&["--no-headers", ":string"]).arg("data.csv");
+
+    // The header row is data, so every column has a string in it.
+    let got: Vec<Vec<String>> = wrk.read_stdout(&mut cmd);
+    assert_eq!(got[0], svec!["n", "x", "when", "ok", "name", "empty"]);
+}
+
+#[test]
+fn select_types_stdin() {
+    let wrk = Workdir::new("select_types_stdin");
+    let mut cmd = wrk.command("select");
+    cmd.arg(":numeric");
+    wrk.assert_err(&mut cmd);
+}
+
+#[test]
+fn select_types_other_commands() {
+    let wrk = Workdir::new("select_types_other_commands");
+    setup_types(&wrk);
+    let mut cmd = wrk.command("stats");
+    cmd.args(&["--select", ":numeric"]).arg("data.csv");
+
+    let got: Vec<Vec<String>> = wrk.read_stdout(&mut cmd);
+    let fields: Vec<&str> = got[1..].iter().map(|row| &*row[0]).collect();
+    assert_eq!(fields, ["n", "x"]);
+}
@@ -142,3 +142,110 @@ fn slice_index_withindex() {
 fn slice_index_no_headers_withindex() {
     test_index("slice_index_no_headers_withindex", 1, "b", false, true);
 }
+
+fn test_slice_key(name: &str, headers: bool, use_index: bool) {
+    let wrk = Workdir::new(name);
+    let mut data = vec![
+        svec!["a", "1"], svec!["b", "2"], svec!["a", "3"], svec!["a", "4"],
+    ];
+    if headers { data.insert(0, svec!["letter", "number"]); }
+    wrk.create("in.csv", data);
+    if use_index {
+        let mut cmd = wrk.command("index");
+        cmd.args(&["--columns", "1", "in.csv"]);
+        wrk.run(&mut cmd);
+    }
+
+    let mut cmd = wrk.command("slice");
+    cmd.args(&["--key", "a", "--key-columns", "1", "--start", "1"]);
+    cmd.arg("in.csv");
+    if !headers {
+        cmd.arg("--no-headers");
+    }
+
+    let got: Vec<Vec<String>> = wrk.read_stdout(&mut cmd);
+    let mut expected = vec![svec!["a", "3"], svec!["a", "4"]];
+    if headers { expected.insert(0, svec!["letter", "number"]); }
+    assert_eq!(got, expected);
+}
+
+#[test]
+fn slice_key() {
+    test_slice_key("slice_key", true, false);
+}
+#[test]
+fn slice_key_no_headers() {
+    test_slice_key("slice_key_no_headers", false, false);
+}
+#[test]
+fn slice_key_withindex() {
+    test_slice_key("slice_key_withindex", true, true);
+}
+#[test]
+fn slice_key_no_headers_withindex() {
+    test_slice_key("slice_key_no_headers_withindex", false, true);
+}
+
+#[test]
+fn slice_key_quoted() {
+    let wrk = Workdir::new("slice_key_quoted");
+    let data = vec![
+        svec!["city", "zip", "n"],
+        svec!["Boston, MA", "02108", "1"],
+        svec!["Boston", "MA", "2"],
+        svec!["Boston, MA", "02108", "3"],
+        svec!["Boston, MA", "02109", "4"],
+    ];
+    wrk.create("in.csv", data.clone());
+    let run = |wrk: &Workdir| {
+        let mut cmd = wrk.command("slice");
+        cmd.args(&["--key", "\"Boston, MA\",02108", "--key-columns", "1,2"]);
+        cmd.arg("in.csv");
+        let got: Vec<Vec<String>> = wrk.read_stdout(&mut cmd);
+        got
+    };
+    let expected = vec![
+        svec!["city", "zip", "n"],
+        svec!["Boston, MA", "02108", "1"],
+        svec!["Boston, MA", "02108", "3"],
+    ];
+    assert_eq!(run(&wrk), expected);
+
+    let wrk = Workdir::new("slice_key_quoted_withindex");
+    wrk.create("in.csv", data);
+    let mut cmd = wrk.command("index");
+    cmd.args(&["--columns", "1,2", "in.csv"]);
+    wrk.run(&mut cmd);
+    assert_eq!(run(&wrk), expected);
+}
+
+#[test]
+fn slice_key_delimiter() {
+    let run = |name: &str, use_index: bool| {
+        let wrk = Workdir::new(name);
+        wrk.create("in.csv", vec![
+            svec!["city", "zip", "n"],
+            svec!["Boston, MA", "02108", "1"],
+            svec!["Boston; MA", "02108", "2"],
+            svec!["Boston, MA", "02108", "3"],
+            svec!["Boston; MA", "02109", "4"],
+        ]);
+        let mut cmd = wrk.command("fmt");
+        cmd.args(&["--out-delimiter", ";", "-o", "in.ssv", "in.csv"]);
+        wrk.run(&mut cmd);
+        if use_index {
+            let mut cmd = wrk.command("index");
+            cmd.args(&["-d", ";", "--columns", "1,2", "in.ssv"]);
+            wrk.run(&mut cmd);
+        }
+
+        let mut cmd = wrk.command("slice");
+        cmd.args(&["-d", ";", "--key", "\"Boston; MA\";02108"]);
+        cmd.args(&["--key-columns", "1,2", "in.ssv"]);
+        let got: String = wrk.stdout(&mut cmd);
+        got
+    };
+    let expected = "city,zip,n\nBoston; MA,02108,2";
+    assert_eq!(run("slice_key_delimiter", false), expected);
+    assert_eq!(run("slice_key_delimiter_withindex", true), expected);
+}
@@ -79,6 +79,33 @@ fn sort_numeric() {
     assert_eq!(got, expected);
 }
 
+#[test]
+fn sort_numeric_multi_column() {
+    let wrk = Workdir::new("sort_numeric_multi_column");
+    wrk.create("in.csv", vec![
+        svec!["N", "S"],
+        svec!["x", "2"],
+        svec!["y", "1"],
+        svec!["3", "b"],
+        svec!["3", "a"],
+    ]);
+
+    let mut cmd = wrk.command("sort");
+    cmd.arg("-N").arg("in.csv");
+
+    let got: Vec<Vec<String>> = wrk.read_stdout(&mut cmd);
+    let expected = vec![
+        svec!["N", "S"],
+        // Once both fields aren't numbers, the records compare equal and
+        // later columns are not looked at.
+        svec!["x", "2"],
+        svec!["y", "1"],
+        svec!["3", "b"],
+        svec!["3", "a"],
+    ];
+    assert_eq!(got, expected);
+}
+
 #[test]
 fn sort_numeric_non_natural() {
     let wrk = Workdir::new("sort_numeric_non_natural");
@@ -128,6 +155,405 @@ fn sort_reverse() {
     assert_eq!(got, expected);
 }
 
+fn prop_sort_external(name: &str, rows: CsvData, headers: bool) -> bool {
+    let wrk = Workdir::new(name);
+    wrk.create("in.csv", rows);
+
+    let mut cmd = wrk.command("sort");
+    cmd.arg("in.csv");
+    if !headers { cmd.arg("--no-headers"); }
+    let expected: String = wrk.stdout(&mut cmd);
+
+    let mut cmd = wrk.command("sort");
+    cmd.arg("in.csv").args(&["--buffer-size", "16"]).args(&["--tmpdir", "."]);
+    if !headers { cmd.arg("--no-headers"); }
+    let got: String = wrk.stdout(&mut cmd);
+    rassert_eq!(got, expected)
+}
+
+#[test]
+fn prop_sort_external_headers() {
+    fn p(rows: CsvData) -> bool {
+        prop_sort_external("prop_sort_external_headers", rows, true)
+    }
+    qcheck(p as fn(CsvData) -> bool);
+}
+
+#[test]
+fn prop_sort_external_no_headers() {
+    fn p(rows: CsvData) -> bool {
+        prop_sort_external("prop_sort_external_no_headers", rows, false)
+    }
+    qcheck(p as fn(CsvData) -> bool);
+}
+
+#[test]
+fn sort_external_numeric_reverse() {
+    let wrk = Workdir::new("sort_external_numeric_reverse");
+    wrk.create("in.csv", vec![
+        svec!["N", "S"],
+        svec!["10", "a"],
+        svec!["LETTER", "b"],
+        svec!["2", "c"],
+        svec!["2", "d"],
+        svec!["1", "e"],
+    ]);
+
+    let mut cmd = wrk.command("sort");
+    cmd.args(&["-N", "-R", "-s", "N"]).args(&["--buffer-size", "1"]);
+    cmd.args(&["--tmpdir", "."]).arg("in.csv");
+
+    let got: Vec<Vec<String>> = wrk.read_stdout(&mut cmd);
+    let expected = vec![
+        svec!["N", "S"],
+        svec!["10", "a"],
+        svec!["2", "c"],
+        svec!["2", "d"],
+        svec!["1", "e"],
+        svec!["LETTER", "b"],
+    ];
+    assert_eq!(got, expected);
+
+    // All temporary files should be cleaned up.
+    let leftover = ::std::fs::read_dir(wrk.path("."))
+        .unwrap()
+        .filter(|e| {
+            let name = e.as_ref().unwrap().file_name();
+            name.to_string_lossy().starts_with("xsv-sort-")
+        })
+        .count();
+    assert_eq!(leftover, 0);
+}
+
+#[test]
+fn sort_external_bad_size() {
+    let wrk = Workdir::new("sort_external_bad_size");
+    wrk.create("in.csv", vec![svec!["1"]]);
+
+    let mut cmd = wrk.command("sort");
+    cmd.args(&["--buffer-size", "lots"]).arg("in.csv");
+    wrk.assert_err(&mut cmd);
+}
+
+#[test]
+fn sort_external_size_overflow() {
+    let wrk = Workdir::new("sort_external_size_overflow");
+    wrk.create("in.csv", vec![svec!["1"]]);
+
+    let mut cmd = wrk.command("sort");
+    cmd.args(&["--buffer-size", "99999999999999999G"]).arg("in.csv");
+    wrk.assert_err(&mut cmd);
+}
+
+#[test]
+fn sort_external_many_runs() {
+    let wrk = Workdir::new("sort_external_many_runs");
+    // Every record gets its own run, so there are more runs than can be
+    // merged at once.
+    let mut rows = vec![svec!["n", "i"]];
+    for i in 0..300 {
+        rows.push(vec![((i * 7) % 10).to_string(), i.to_string()]);
+    }
+    wrk.create("in.csv", rows.clone());
+
+    let mut cmd = wrk.command("sort");
+    cmd.args(&["-N", "-s", "n", "--buffer-size", "1", "--tmpdir", "."])
+       .arg("in.csv");
+
+    let got: Vec<Vec<String>> = wrk.read_stdout(&mut cmd);
+    let mut expected = rows[1..].to_vec();
+    expected.sort_by_key(|r| r[0].parse::<u32>().unwrap());
+    expected.insert(0, rows[0].clone());
+    assert_eq!(got, expected);
+}
+
+#[test]
+fn sort_external_jobs() {
+    let wrk = Workdir::new("sort_external_jobs");
+    wrk.create("in.csv", vec![svec!["1"]]);
+
+    let mut cmd = wrk.command("sort");
+    cmd.args(&["--buffer-size", "1K", "--jobs", "4"]).arg("in.csv");
+    wrk.assert_err(&mut cmd);
+}
+
+#[test]
+fn sort_unique_normalize() {
+    let wrk = Workdir::new("sort_unique_normalize");
+    wrk.create("in.csv", vec![
+        svec!["id", "v"],
+        svec!["8", "1"],
+        svec!["007", "2"],
+        svec!["7.0", "3"],
+        svec!["ID-7", "4"],
+    ]);
+
+    let mut cmd = wrk.command("sort");
+    cmd.args(&["-s", "id", "--unique", "--normalize", "numeric"])
+       .args(&["--key-regex", "[0-9.]+"])
+       .arg("in.csv");
+    let got: Vec<Vec<String>> = wrk.read_stdout(&mut cmd);
+    let expected = vec![
+        svec!["id", "v"],
+        svec!["007", "2"],
+        svec!["8", "1"],
+    ];
+    assert_eq!(got, expected);
+}
+
+#[test]
+fn sort_order_mixed() {
+    let wrk = Workdir::new("sort_order_mixed");
+    wrk.create("in.csv", vec![
+        svec!["region", "revenue", "item"],
+        svec!["west", "5", "a10"],
+        svec!["east", "10", "a2"],
+        svec!["west", "20", "a1"],
+        svec!["east", "9", "a3"],
+        svec!["east", "10", "a1"],
+    ]);
+
+    let mut cmd = wrk.command("sort");
+    cmd.args(&["-s", "region,revenue,item"]);
+    cmd.args(&["--order", "asc,desc:num,natural"]).arg("in.csv");
+
+    let got: Vec<Vec<String>> = wrk.read_stdout(&mut cmd);
+    let expected = vec![
+        svec!["region", "revenue", "item"],
+        svec!["east", "10", "a1"],
+        svec!["east", "10", "a2"],
+        svec!["east", "9", "a3"],
+        svec!["west", "20", "a1"],
+        svec!["west", "5", "a10"],
+    ];
+    assert_eq!(got, expected);
+}
+
+#[test]
+fn sort_order_natural() {
+    let wrk = Workdir::new("sort_order_natural");
+    wrk.create("in.csv", vec![
+        svec!["file10.txt"],
+        svec!["file2.txt"],
+        svec!["file1.txt"],
+        svec!["file02.csv"],
+    ]);
+
+    let mut cmd = wrk.command("sort");
+    cmd.args(&["--order", "natural"]).arg("--no-headers").arg("in.csv");
+
+    let got: Vec<Vec<String>> = wrk.read_stdout(&mut cmd);
+    let expected = vec![
+        svec!["file1.txt"],
+        svec!["file02.csv"],
+        svec!["file2.txt"],
+        svec!["file10.txt"],
+    ];
+    assert_eq!(got, expected);
+}
+
+#[test]
+fn sort_order_nocase() {
+    let wrk = Workdir::new("sort_order_nocase");
+    wrk.create("in.csv", vec![
+        svec!["b"],
+        svec!["B"],
+        svec!["a"],
+        svec!["C"],
+    ]);
+
+    let mut cmd = wrk.command("sort");
+    cmd.args(&["--order", "nocase"]).arg("--no-headers").arg("in.csv");
+
+    let got: Vec<Vec<String>> = wrk.read_stdout(&mut cmd);
+    let expected = vec![svec!["a"], svec!["b"], svec!["B"], svec!["C"]];
+    assert_eq!(got, expected);
+}
+
+#[test]
+fn sort_order_nulls_last() {
+    let wrk = Workdir::new("sort_order_nulls_last");
+    wrk.create("in.csv", vec![
+        svec!["N"],
+        svec!["3"],
+        svec![""],
+        svec!["1"],
+        svec!["2"],
+    ]);
+
+    let mut cmd = wrk.command("sort");
+    cmd.args(&["--order", "num:nulls-last"]).arg("in.csv");
+    let got: Vec<Vec<String>> = wrk.read_stdout(&mut cmd);
+    let expected = vec![
+        svec!["N"], svec!["1"], svec!["2"], svec!["3"], svec![""],
+    ];
+    assert_eq!(got, expected);
+
+    // The placement of nulls doesn't change with the direction.
+    let mut cmd = wrk.command("sort");
+    cmd.args(&["--order", "desc:num:nulls-last"]).arg("in.csv");
+    let got: Vec<Vec<String>> = wrk.read_stdout(&mut cmd);
+    let expected = vec![
+        svec!["N"], svec!["3"], svec!["2"], svec!["1"], svec![""],
+    ];
+    assert_eq!(got, expected);
+}
+
+#[test]
+fn sort_order_wrong_count() {
+    let wrk = Workdir::new("sort_order_wrong_count");
+    wrk.create("in.csv", vec![svec!["a", "b", "c"], svec!["1", "2", "3"]]);
+
+    let mut cmd = wrk.command("sort");
+    cmd.args(&["-s", "a,b,c", "--order", "asc,desc"]).arg("in.csv");
+    wrk.assert_err(&mut cmd);
+}
+
+#[test]
+fn sort_order_bad_word() {
+    let wrk = Workdir::new("sort_order_bad_word");
+    wrk.create("in.csv", vec![svec!["a"], svec!["1"]]);
+
+    let mut cmd = wrk.command("sort");
+    cmd.args(&["--order", "sideways"]).arg("in.csv");
+    wrk.assert_err(&mut cmd);
+}
+
+#[test]
+fn sort_stable() {
+    let wrk = Workdir::new("sort_stable");
+    wrk.create("in.csv", vec![
+        svec!["k", "v"],
+        svec!["b", "1"],
+        svec!["a", "2"],
+        svec!["b", "3"],
+        svec!["a", "4"],
+        svec!["b", "5"],
+    ]);
+    let expected = vec![
+        svec!["k", "v"],
+        svec!["a", "2"],
+        svec!["a", "4"],
+        svec!["b", "1"],
+        svec!["b", "3"],
+        svec!["b", "5"],
+    ];
+
+    let mut cmd = wrk.command("sort");
+    cmd.args(&["-s", "k", "--stable"]).arg("in.csv");
+    let got: Vec<Vec<String>> = wrk.read_stdout(&mut cmd);
+    assert_eq!(got, expected);
+
+    let mut cmd = wrk.command("sort");
+    cmd.args(&["-s", "k", "--stable", "--buffer-size", "1"]);
+    cmd.args(&["--tmpdir", "."]).arg("in.csv");
+    let got: Vec<Vec<String>> = wrk.read_stdout(&mut cmd);
+    assert_eq!(got, expected);
+}
+
+#[test]
+fn sort_stable_reverse() {
+    let wrk = Workdir::new("sort_stable_reverse");
+    wrk.create("in.csv", vec![
+        svec!["b", "1"],
+        svec!["a", "2"],
+        svec!["b", "3"],
+        svec!["a", "4"],
+    ]);
+
+    let mut cmd = wrk.command("sort");
+    cmd.args(&["-s", "1", "-R", "--no-headers"]).arg("in.csv");
+    let got: Vec<Vec<String>> = wrk.read_stdout(&mut cmd);
+    let expected = vec![
+        svec!["b", "1"],
+        svec!["b", "3"],
+        svec!["a", "2"],
+        svec!["a", "4"],
+    ];
+    assert_eq!(got, expected);
+}
+
+#[test]
+fn sort_unique() {
+    let wrk = Workdir::new("sort_unique");
+    wrk.create("in.csv", vec![
+        svec!["k", "v"],
+        svec!["b", "1"],
+        svec!["A", "2"],
+        svec!["b", "3"],
+        svec!["a", "4"],
+    ]);
+    let expected = vec![
+        svec!["k", "v"],
+        svec!["A", "2"],
+        svec!["b", "1"],
+    ];
+
+    let mut cmd = wrk.command("sort");
+    cmd.args(&["-s", "k", "--order", "nocase", "--unique"]).arg("in.csv");
+    let got: Vec<Vec<String>> = wrk.read_stdout(&mut cmd);
+    assert_eq!(got, expected);
+
+    let mut cmd = wrk.command("sort");
+    cmd.args(&["-s", "k", "--order", "nocase", "--unique"]);
+    cmd.args(&["--buffer-size", "1", "--tmpdir", "."]).arg("in.csv");
+    let got: Vec<Vec<String>> = wrk.read_stdout(&mut cmd);
+    assert_eq!(got, expected);
+}
+
+fn prop_sort_parallel(name: &str, rows: CsvData, indexed: bool) -> bool {
+    let wrk = Workdir::new(name);
+    if indexed {
+        wrk.create_indexed("in.csv", rows);
+    } else {
+        wrk.create("in.csv", rows);
+    }
+
+    let mut cmd = wrk.command("sort");
+    cmd.arg("in.csv").args(&["--jobs", "1"]);
+    let expected: String = wrk.stdout(&mut cmd);
+
+    let mut cmd = wrk.command("sort");
+    cmd.arg("in.csv").args(&["--jobs", "4"]);
+    let got: String = wrk.stdout(&mut cmd);
+    rassert_eq!(got, expected)
+}
+
+#[test]
+fn prop_sort_parallel_index() {
+    fn p(rows: CsvData) -> bool {
+        prop_sort_parallel("prop_sort_parallel_index", rows, true)
+    }
+    qcheck(p as fn(CsvData) -> bool);
+}
+
+#[test]
+fn prop_sort_parallel_no_index() {
+    fn p(rows: CsvData) -> bool {
+        prop_sort_parallel("prop_sort_parallel_no_index", rows, false)
+    }
+    qcheck(p as fn(CsvData) -> bool);
+}
+
+#[test]
+fn sort_parallel_index_stable() {
+    let wrk = Workdir::new("sort_parallel_index_stable");
+    let mut rows = vec![svec!["k", "v"]];
+    for i in 0..50 {
+        rows.push(vec![(i % 3).to_string(), i.to_string()]);
+    }
+    wrk.create_indexed("in.csv", rows.clone());
+
+    let mut cmd = wrk.command("sort");
+    cmd.args(&["-s", "k", "--jobs", "4"]).arg("in.csv");
+    let got: Vec<Vec<String>> = wrk.read_stdout(&mut cmd);
+
+    let header = rows.remove(0);
+    rows.sort_by(|r1, r2| r1[0].cmp(&r2[0]));
+    rows.insert(0, header);
+    assert_eq!(got, rows);
+}
+
 /// Order `a` and `b` lexicographically using `Ord`
 pub fn iter_cmp<A, L, R>(mut a: L, mut b: R) -> cmp::Ordering
         where A: Ord, L: Iterator<Item=A>, R: Iterator<Item=A> {
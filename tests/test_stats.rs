@@ -93,6 +93,14 @@ fn get_field_value(wrk: &Workdir, cmd: &mut process::Command, field: &str)
     if field == "median" { cmd.arg("--median"); }
     if field == "cardinality" { cmd.arg("--cardinality"); }
     if field == "mode" { cmd.arg("--mode"); }
+    match field {
+        "variance" | "skewness" | "kurtosis" | "cv" => {
+            cmd.arg("--moments");
+        }
+        "zeros" | "negatives" | "null_ratio" => { cmd.arg("--counts"); }
+        "true_count" | "true_ratio" => { cmd.arg("--booleans"); }
+        _ => {}
+    }
 
     let mut rows: Vec<Vec<String>> = wrk.read_stdout(cmd);
     let headers = rows.remove(0);
@@ -123,6 +131,19 @@ stats_tests!(stats_infer_int_float, "type", &["1", "1.2"], "Float");
 stats_tests!(stats_infer_float_int, "type", &["1.2", "1"], "Float");
 stats_tests!(stats_infer_null_int_float_unicode, "type",
              &["", "1", "1.2", "a"], "Unicode");
+stats_tests!(stats_infer_date, "type", &["2018-01-31", ""], "Date");
+stats_tests!(stats_infer_datetime, "type",
+             &["2018-01-31T10:00:00", "2018-01-31 10:00:00.5"], "DateTime");
+stats_tests!(stats_infer_datetime_offset, "type",
+             &["2018-01-31T10:00:00+02:00"], "DateTime");
+stats_tests!(stats_infer_date_datetime, "type",
+             &["2018-01-31", "2018-01-31T10:00:00"], "DateTime");
+stats_tests!(stats_infer_date_unicode, "type",
+             &["2018-01-31", "a"], "Unicode");
+stats_tests!(stats_infer_date_int, "type", &["2018-01-31", "1"], "Unicode");
+stats_tests!(stats_infer_bool, "type", &["yes", "No", "", "Y"], "Boolean");
+stats_tests!(stats_infer_bool_unicode, "type", &["true", "maybe"], "Unicode");
+stats_tests!(stats_infer_bool_int, "type", &["true", "1"], "Unicode");
 
 stats_tests!(stats_no_mean, "mean", &["a"], "");
 stats_tests!(stats_no_stddev, "stddev", &["a"], "");
@@ -150,12 +171,25 @@ stats_tests!(stats_sum_mixed3, "sum", &["1.5", "hi", "2.8"], "4.3");
 stats_tests!(stats_sum_nulls1, "sum", &["1", "", "2"], "3");
 stats_tests!(stats_sum_nulls2, "sum", &["", "1", "2"], "3");
 
+stats_tests!(stats_true_count, "true_count",
+             &["true", "FALSE", "", "True", "false"], "2");
+stats_tests!(stats_true_ratio, "true_ratio",
+             &["y", "n", "", "n", "n"], "0.25");
+stats_tests!(stats_no_true_count, "true_count", &["1", "2"], "");
+
 stats_tests!(stats_min, "min", &["2", "1.1"], "1.1");
 stats_tests!(stats_max, "max", &["2", "1.1"], "2");
 stats_tests!(stats_min_mix, "min", &["2", "a", "1.1"], "1.1");
 stats_tests!(stats_max_mix, "max", &["2", "a", "1.1"], "a");
 stats_tests!(stats_min_null, "min", &["", "2", "1.1"], "1.1");
 stats_tests!(stats_max_null, "max", &["2", "1.1", ""], "2");
+stats_tests!(stats_min_date, "min",
+             &["2018/02/01", "2017-12-31", "2018-01-01"], "2017-12-31");
+stats_tests!(stats_max_date, "max",
+             &["2018/02/01", "2017-12-31", "2018-01-01"], "2018-02-01");
+stats_tests!(stats_max_datetime, "max",
+             &["2018-01-31T10:00:00+02:00", "2018-01-31 09:00:00"],
+             "2018-01-31T09:00:00");
 
 stats_tests!(stats_len_min, "min_length", &["aa", "a"], "1");
 stats_tests!(stats_len_max, "max_length", &["a", "aa"], "2");
@@ -171,6 +205,21 @@ stats_tests!(stats_mean_mix, "mean", &["5", "15.1", "9.9"], "10");
 stats_tests!(stats_stddev_mix, "stddev", &["1", "2.1", "2.9"],
              "0.7788880963698614");
 
+stats_tests!(stats_variance, "variance", &["1", "2", "3"],
+             "0.6666666666666666");
+stats_tests!(stats_variance_null, "variance", &["1", "", "3"], "1");
+stats_tests!(stats_skewness, "skewness", &["1", "2", "3"], "0");
+stats_tests!(stats_skewness_right, "skewness", &["1", "1", "1", "5"],
+             "1.1547005383792515");
+stats_tests!(stats_kurtosis, "kurtosis", &["1", "2", "3"], "-1.5");
+stats_tests!(stats_no_kurtosis, "kurtosis", &["2", "2"], "");
+stats_tests!(stats_cv, "cv", &["1", "3"], "0.5");
+stats_tests!(stats_no_cv, "cv", &["a", "3"], "");
+stats_tests!(stats_zeros, "zeros", &["0", "1.5", "0.0", ""], "2");
+stats_tests!(stats_negatives, "negatives", &["-1", "1", "-0.5"], "2");
+stats_tests!(stats_no_negatives, "negatives", &["-1", "a"], "");
+stats_tests!(stats_null_ratio, "null_ratio", &["a", "", "b", ""], "0.5");
+
 stats_tests!(stats_cardinality, "cardinality", &["a", "b", "a"], "2");
 stats_tests!(stats_mode, "mode", &["a", "b", "a"], "a");
 stats_tests!(stats_mode_null, "mode", &["", "a", "b", "a"], "a");
@@ -180,6 +229,7 @@ stats_tests!(stats_median_even, "median", &["1", "2", "3", "4"], "2.5");
 stats_tests!(stats_median_even_null, "median",
              &["", "1", "2", "3", "4"], "2.5");
 stats_tests!(stats_median_mix, "median", &["1", "2.5", "3"], "2.5");
+stats_tests!(stats_median_nan, "median", &["1", "NaN", "3"], "2");
 
 mod stats_infer_nothing {
     // Only test CSV data with headers.
@@ -213,3 +263,398 @@ mod stats_header_fields {
     stats_test_headers!(stats_header_field_name, "field", &["a"], "header");
     stats_test_no_headers!(stats_header_no_field_name, "field", &["a"], "0");
 }
+
+fn test_stats_group_by(name: &str, indexed: bool) {
+    let wrk = Workdir::new(name);
+    let rows = vec![
+        svec!["day", "n"],
+        svec!["mon", "1"],
+        svec!["tue", "10"],
+        svec!["mon", "3"],
+        svec!["wed", "a"],
+        svec!["tue", "20"],
+    ];
+    if indexed {
+        wrk.create_indexed("in.csv", rows);
+    } else {
+        wrk.create("in.csv", rows);
+    }
+    let mut cmd = wrk.command("stats");
+    cmd.args(&["--group-by", "day", "--select", "n"]).arg("in.csv");
+    if indexed {
+        cmd.args(&["--jobs", "3"]);
+    }
+
+    let got: Vec<Vec<String>> = wrk.read_stdout(&mut cmd);
+    let got: Vec<Vec<String>> = got.into_iter()
+        .map(|row| row[0..5].to_vec())
+        .collect();
+    let expected = vec![
+        svec!["day", "field", "type", "sum", "min"],
+        svec!["mon", "n", "Integer", "4", "1"],
+        svec!["tue", "n", "Integer", "30", "10"],
+        svec!["wed", "n", "Unicode", "", "a"],
+    ];
+    assert_eq!(got, expected);
+}
+
+#[test]
+fn stats_group_by() {
+    test_stats_group_by("stats_group_by", false);
+}
+
+#[test]
+fn stats_group_by_index() {
+    test_stats_group_by("stats_group_by_index", true);
+}
+
+fn test_quantiles(name: &str, approx: bool) {
+    let wrk = Workdir::new(name);
+    let mut rows = vec![svec!["n"]];
+    for i in 1..102 {
+        rows.push(vec![i.to_string()]);
+    }
+    wrk.create("in.csv", rows);
+    let mut cmd = wrk.command("stats");
+    cmd.args(&["--median", "--quantiles", "0.25,0.75,0.9"]).arg("in.csv");
+    if approx {
+        cmd.arg("--approx");
+    }
+
+    let got: Vec<Vec<String>> = wrk.read_stdout(&mut cmd);
+    let n = got[0].len();
+    assert_eq!(&got[0][n - 5..], &*svec!["median", "q0.25", "q0.75",
+                                         "q0.9", "iqr"]);
+    let values: Vec<f64> = got[1][n - 5..].iter()
+        .map(|v| v.parse().unwrap())
+        .collect();
+    let expected = [51.0, 26.0, 76.0, 91.0, 50.0];
+    for (v, e) in values.iter().zip(&expected) {
+        assert!((v - e).abs() <= if approx { 1.0 } else { 0.0 },
+                "got {:?}, expected {:?}", values, expected);
+    }
+}
+
+#[test]
+fn stats_quantiles() {
+    test_quantiles("stats_quantiles", false);
+}
+
+#[test]
+fn stats_quantiles_approx() {
+    test_quantiles("stats_quantiles_approx", true);
+}
+
+fn test_quantiles_nan(name: &str, approx: bool) {
+    let wrk = Workdir::new(name);
+    wrk.create("in.csv", vec![
+        svec!["n"], svec!["1"], svec!["NaN"], svec!["3"],
+    ]);
+    let mut cmd = wrk.command("stats");
+    cmd.args(&["--median", "--quantiles", "0,0.5,1"]).arg("in.csv");
+    if approx {
+        cmd.arg("--approx");
+    }
+
+    let got: Vec<Vec<String>> = wrk.read_stdout(&mut cmd);
+    let n = got[0].len();
+    assert_eq!(&got[1][n - 4..], &*svec!["2", "1", "2", "3"]);
+}
+
+#[test]
+fn stats_quantiles_nan() {
+    test_quantiles_nan("stats_quantiles_nan", false);
+}
+
+#[test]
+fn stats_quantiles_nan_approx() {
+    test_quantiles_nan("stats_quantiles_nan_approx", true);
+}
+
+#[test]
+fn stats_quantiles_invalid() {
+    let wrk = Workdir::new("stats_quantiles_invalid");
+    wrk.create("in.csv", vec![svec!["n"], svec!["1"]]);
+    let mut cmd = wrk.command("stats");
+    cmd.args(&["--quantiles", "0.5,1.5"]).arg("in.csv");
+    wrk.assert_err(&mut cmd);
+}
+
+fn test_cardinality_approx(name: &str, index: bool) {
+    let wrk = Workdir::new(name);
+    let mut rows = vec![svec!["n"]];
+    for i in 0..20000 {
+        rows.push(vec![(i % 5000).to_string()]);
+    }
+    wrk.create("in.csv", rows);
+    if index {
+        let mut cmd = wrk.command("index");
+        cmd.arg("in.csv");
+        wrk.run(&mut cmd);
+    }
+    let mut cmd = wrk.command("stats");
+    cmd.args(&["--cardinality", "--approx", "--select", "n"]).arg("in.csv");
+
+    let got: Vec<Vec<String>> = wrk.read_stdout(&mut cmd);
+    let card: f64 = got[1].last().unwrap().parse().unwrap();
+    assert!((card - 5000.0).abs() < 150.0, "got cardinality {}", card);
+}
+
+#[test]
+fn stats_cardinality_approx() {
+    test_cardinality_approx("stats_cardinality_approx", false);
+}
+
+#[test]
+fn stats_cardinality_approx_index() {
+    test_cardinality_approx("stats_cardinality_approx_index", true);
+}
+
+#[test]
+fn stats_cardinality_approx_invalid_precision() {
+    let wrk = Workdir::new("stats_cardinality_approx_invalid_precision");
+    wrk.create("in.csv", vec![svec!["n"], svec!["1"]]);
+    let mut cmd = wrk.command("stats");
+    cmd.args(&["--approx", "--precision", "30"]).arg("in.csv");
+    wrk.assert_err(&mut cmd);
+}
+
+#[test]
+fn stats_date_formats() {
+    let wrk = Workdir::new("stats_date_formats");
+    wrk.create("in.csv", vec![
+        svec!["d"], svec!["31/01/2018"], svec!["1/2/2018"], svec!["5/1/2018"],
+    ]);
+    let mut cmd = wrk.command("stats");
+    cmd.args(&["--date-formats", "%d/%m/%Y"]).arg("in.csv");
+
+    assert_eq!(get_field_value(&wrk, &mut cmd, "type"), "Date");
+    assert_eq!(get_field_value(&wrk, &mut cmd, "min"), "2018-01-05");
+    assert_eq!(get_field_value(&wrk, &mut cmd, "max"), "2018-02-01");
+}
+
+#[test]
+fn stats_bool_tokens() {
+    let wrk = Workdir::new("stats_bool_tokens");
+    wrk.create("in.csv", vec![
+        svec!["b"], svec!["1"], svec!["0"], svec!["1"], svec!["1"],
+    ]);
+    let value = |field: &str| {
+        let mut cmd = wrk.command("stats");
+        cmd.args(&["--true-values", "1", "--false-values", "0"]);
+        cmd.arg("in.csv");
+        get_field_value(&wrk, &mut cmd, field)
+    };
+
+    assert_eq!(value("type"), "Boolean");
+    assert_eq!(value("true_count"), "3");
+    assert_eq!(value("true_ratio"), "0.75");
+}
+
+#[test]
+fn stats_default_headers() {
+    let wrk = Workdir::new("stats_default_headers");
+    wrk.create("in.csv", vec![svec!["b"], svec!["yes"], svec!["no"]]);
+    let mut cmd = wrk.command("stats");
+    cmd.arg("in.csv");
+
+    let got: Vec<Vec<String>> = wrk.read_stdout(&mut cmd);
+    assert_eq!(got[0], svec!["field", "type", "sum", "min", "max",
+                             "min_length", "max_length", "mean", "stddev"]);
+}
+
+#[test]
+fn stats_everything_headers() {
+    let wrk = Workdir::new("stats_everything_headers");
+    wrk.create("in.csv", vec![svec!["n"], svec!["1"], svec!["2"]]);
+    let mut cmd = wrk.command("stats");
+    cmd.args(&["--everything", "in.csv"]);
+
+    let got: Vec<Vec<String>> = wrk.read_stdout(&mut cmd);
+    assert_eq!(got[0], svec![
+        "field", "type", "sum", "min", "max", "min_length", "max_length",
+        "mean", "stddev", "median", "mode", "cardinality",
+    ]);
+}
+
+fn setup_histogram(name: &str, index: bool) -> Workdir {
+    let wrk = Workdir::new(name);
+    let mut rows = vec![svec!["g", "n", "s"]];
+    for i in 0..100 {
+        rows.push(vec![(["a", "b"][i % 2]).to_owned(), i.to_string(),
+                       "x".to_owned()]);
+    }
+    if index {
+        wrk.create_indexed("in.csv", rows);
+    } else {
+        wrk.create("in.csv", rows);
+    }
+    wrk
+}
+
+fn test_histogram(name: &str, index: bool) {
+    let wrk = setup_histogram(name, index);
+    let mut cmd = wrk.command("stats");
+    cmd.args(&["--histogram", "4"]).arg("in.csv");
+
+    let got: Vec<Vec<String>> = wrk.read_stdout(&mut cmd);
+    let expected = vec![
+        svec!["field", "lower", "upper", "count"],
+        svec!["n", "0", "24.75", "25"],
+        svec!["n", "24.75", "49.5", "25"],
+        svec!["n", "49.5", "74.25", "25"],
+        svec!["n", "74.25", "99", "25"],
+    ];
+    assert_eq!(got, expected);
+}
+
+#[test]
+fn stats_histogram() {
+    test_histogram("stats_histogram", false);
+}
+
+#[test]
+fn stats_histogram_index() {
+    test_histogram("stats_histogram_index", true);
+}
+
+#[test]
+fn stats_histogram_quantile_group_by() {
+    let wrk = setup_histogram("stats_histogram_quantile_group_by", false);
+    let mut cmd = wrk.command("stats");
+    cmd.args(&["--histogram", "2", "--bin-type", "quantile"])
+       .args(&["--group-by", "g", "--select", "n"])
+       .arg("in.csv");
+
+    let got: Vec<Vec<String>> = wrk.read_stdout(&mut cmd);
+    let expected = vec![
+        svec!["g", "field", "lower", "upper", "count"],
+        svec!["a", "n", "0", "49", "25"],
+        svec!["a", "n", "49", "98", "25"],
+        svec!["b", "n", "1", "50", "25"],
+        svec!["b", "n", "50", "99", "25"],
+    ];
+    assert_eq!(got, expected);
+}
+
+#[test]
+fn stats_histogram_quantile_one_bin() {
+    let wrk = setup_histogram("stats_histogram_quantile_one_bin", false);
+    let mut cmd = wrk.command("stats");
+    cmd.args(&["--histogram", "1", "--bin-type", "quantile"]).arg("in.csv");
+
+    let got: Vec<Vec<String>> = wrk.read_stdout(&mut cmd);
+    let expected = vec![
+        svec!["field", "lower", "upper", "count"],
+        svec!["n", "0", "99", "100"],
+    ];
+    assert_eq!(got, expected);
+}
+
+#[test]
+fn stats_histogram_non_finite() {
+    let wrk = Workdir::new("stats_histogram_non_finite");
+    wrk.create("in.csv", vec![
+        svec!["a"], svec!["1"], svec!["2"], svec!["inf"], svec!["-inf"],
+        svec!["NaN"], svec!["4"],
+    ]);
+    for bin_type in &["width", "quantile"] {
+        let mut cmd = wrk.command("stats");
+        cmd.args(&["--histogram", "3", "--bin-type", bin_type]).arg("in.csv");
+
+        let got: Vec<Vec<String>> = wrk.read_stdout(&mut cmd);
+        assert_eq!(got[0], svec!["field", "lower", "upper", "count"]);
+        assert_eq!(got[1][1], "1");
+        assert_eq!(got[got.len() - 1][2], "4");
+        // Only the finite numbers are counted.
+        let total: u64 = got[1..].iter()
+                                 .map(|row| row[3].parse::<u64>().unwrap())
+                                 .sum();
+        assert_eq!(total, 3);
+    }
+}
+
+#[test]
+fn stats_histogram_bars() {
+    let wrk = setup_histogram("stats_histogram_bars", false);
+    let mut cmd = wrk.command("stats");
+    cmd.args(&["--histogram", "2", "--bars"]).arg("in.csv");
+
+    let got: String = wrk.stdout(&mut cmd);
+    let bar: String = ::std::iter::repeat('#').take(50).collect();
+    let expected = format!("\
+n
+[0, 49.5)  50 |{bar}
+[49.5, 99] 50 |{bar}", bar = bar);
+    assert_eq!(got, expected);
+}
+
+#[test]
+fn stats_histogram_stdin() {
+    let wrk = Workdir::new("stats_histogram_stdin");
+    let mut cmd = wrk.command("stats");
+    cmd.args(&["--histogram", "2"]);
+    wrk.assert_err(&mut cmd);
+}
+
+#[test]
+fn stats_format_json() {
+    let wrk = Workdir::new("stats_format_json");
+    wrk.create("in.csv", vec![
+        svec!["n", "s", "d"],
+        svec!["1", "b", "2018-02-01"],
+        svec!["2.5", "a", ""],
+        svec!["", "a", "2018-01-01"],
+    ]);
+    let mut cmd = wrk.command("stats");
+    cmd.args(&["--format", "json", "--mode"]).arg("in.csv");
+
+    let got: String = wrk.stdout(&mut cmd);
+    let got: serde_json::Value = serde_json::from_str(&got).unwrap();
+    let expected: serde_json::Value = serde_json::from_str(r#"[
+        {"field": "n", "type": "Float", "sum": 3.5, "min": 1.0, "max": 2.5,
+         "min_length": 0, "max_length": 3, "mean": 1.75, "stddev": 0.75,
+         "mode": null},
+        {"field": "s", "type": "Unicode", "sum": null, "min": "a",
+         "max": "b", "min_length": 1, "max_length": 1, "mean": null,
+         "stddev": null, "mode": "a"},
+        {"field": "d", "type": "Date", "sum": null, "min": "2018-01-01",
+         "max": "2018-02-01", "min_length": 0, "max_length": 10,
+         "mean": null, "stddev": null, "mode": null}
+    ]"#).unwrap();
+    assert_eq!(got, expected);
+}
+
+#[test]
+fn stats_format_json_mode() {
+    let wrk = Workdir::new("stats_format_json_mode");
+    wrk.create("in.csv", vec![
+        svec!["i", "f"],
+        svec!["1", "1.50"],
+        svec!["2", "1.50"],
+        svec!["2", "3"],
+    ]);
+    let mut cmd = wrk.command("stats");
+    cmd.args(&["--format", "json", "--mode", "--select", "i,f"]);
+    cmd.arg("in.csv");
+
+    let got: String = wrk.stdout(&mut cmd);
+    let got: serde_json::Value = serde_json::from_str(&got).unwrap();
+    assert_eq!(got[0]["mode"], serde_json::Value::from(2));
+    assert_eq!(got[0]["min"], serde_json::Value::from(1));
+    assert_eq!(got[1]["mode"], serde_json::Value::from(1.5));
+
+    // The CSV output keeps the mode as it is written.
+    let mut cmd = wrk.command("stats");
+    cmd.args(&["--select", "f", "in.csv"]);
+    assert_eq!(get_field_value(&wrk, &mut cmd, "mode"), "1.50");
+}
+
+#[test]
+fn stats_format_invalid() {
+    let wrk = Workdir::new("stats_format_invalid");
+    wrk.create("in.csv", vec![svec!["n"], svec!["1"]]);
+    let mut cmd = wrk.command("stats");
+    cmd.args(&["--format", "xml"]).arg("in.csv");
+    wrk.assert_err(&mut cmd);
+}
@@ -9,6 +9,7 @@ extern crate csv;
 extern crate filetime;
 extern crate quickcheck;
 extern crate rand;
+extern crate serde_json;
 extern crate stats;
 
 use std::fmt;
@@ -35,10 +36,12 @@ mod workdir;
 
 mod test_cat;
 mod test_count;
+mod test_dedup;
 mod test_fixlengths;
 mod test_flatten;
 mod test_fmt;
 mod test_frequency;
+mod test_groupby;
 mod test_headers;
 mod test_index;
 mod test_join;